@@ -0,0 +1,59 @@
+use aoc2021::day03::Readings;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Generates `len` random readings of `width` bits, seeded so runs are repeatable.
+fn generate(len: usize, width: usize) -> Readings {
+    let mut rng = ChaCha8Rng::seed_from_u64(3);
+    (0..len)
+        .map(|_| {
+            format!(
+                "{:0width$b}",
+                rng.gen::<u64>() >> (64 - width),
+                width = width
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .parse()
+        .unwrap()
+}
+
+fn power_consumption(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day03/power_consumption");
+    for len in [1_000, 1_000_000] {
+        let readings = generate(len, 32);
+        let bit_sliced = readings.bit_sliced();
+
+        group.bench_with_input(BenchmarkId::new("readings", len), &readings, |b, r| {
+            b.iter(|| black_box(r.power_consumption()))
+        });
+        group.bench_with_input(BenchmarkId::new("bit_sliced", len), &bit_sliced, |b, r| {
+            b.iter(|| black_box(r.power_consumption()))
+        });
+    }
+    group.finish();
+}
+
+fn life_support_rating(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day03/life_support_rating");
+    for len in [1_000, 1_000_000] {
+        let readings = generate(len, 32);
+        let bit_sliced = readings.bit_sliced();
+
+        group.bench_with_input(BenchmarkId::new("readings", len), &readings, |b, r| {
+            b.iter(|| black_box(r.life_support_rating()))
+        });
+        group.bench_with_input(BenchmarkId::new("bit_sliced", len), &bit_sliced, |b, r| {
+            b.iter(|| black_box(r.life_support_rating()))
+        });
+        group.bench_with_input(BenchmarkId::new("partitioned", len), &readings, |b, r| {
+            b.iter(|| black_box(r.life_support_rating_partitioned()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, power_consumption, life_support_rating);
+criterion_main!(benches);
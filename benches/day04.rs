@@ -0,0 +1,65 @@
+use aoc2021::day04::Bingo;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Generates a game with `boards` 5x5 boards of distinct numbers and a shuffled call sequence,
+/// seeded so runs are repeatable.
+fn generate(boards: usize) -> String {
+    let mut rng = ChaCha8Rng::seed_from_u64(4);
+    let mut numbers: Vec<u8> = (0..100).collect();
+
+    numbers.shuffle(&mut rng);
+    let mut game = numbers
+        .iter()
+        .map(u8::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    for _ in 0..boards {
+        numbers.shuffle(&mut rng);
+        game.push('\n');
+        for row in numbers[..25].chunks(5) {
+            game.push('\n');
+            game.push_str(
+                &row.iter()
+                    .map(|n| format!("{:>2}", n))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+    }
+
+    game
+}
+
+fn play(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day04");
+    for boards in [100, 1_000, 10_000] {
+        let bingo: Bingo = generate(boards).parse().unwrap();
+
+        group.bench_with_input(BenchmarkId::new("play_game", boards), &bingo, |b, bingo| {
+            b.iter_batched(
+                || bingo.clone(),
+                |mut bingo| black_box(bingo.play_game()),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(
+            BenchmarkId::new("play_until_last", boards),
+            &bingo,
+            |b, bingo| {
+                b.iter_batched(
+                    || bingo.clone(),
+                    |mut bingo| black_box(bingo.play_until_last()),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, play);
+criterion_main!(benches);
@@ -0,0 +1,222 @@
+use anyhow::{anyhow, bail};
+use aoc2021::day03::Readings;
+use aoc2021::day04::{Bingo, Event, Goal, Highlight, SearchResult};
+use aoc2021::day05::{Line, Lines, Point, Raster};
+use aoc2021::day06::NonNaiveSchool;
+
+const USAGE: &str = "\
+usage: aoc <day> <command>
+
+commands:
+    day03 report            per-position bit statistics and rating filter trace
+    day04 replay <board>    the state of a board after every number marked on it
+    day04 fastest <board> <first|last>
+                            shortest call sequence making a board win first or last
+    day04 simulate <trials> <seed>
+                            win probabilities for each board under random call orders
+    day05 grid              vent overlap counts drawn as in the puzzle
+    day05 heatmap <path>    vent overlap counts as a PPM (or PGM for a .pgm path) image
+    day05 at <x,y>          the vent lines covering a point
+    day05 within <x,y> <x,y>
+                            the vent lines covering part of a rectangle
+    day05 path <x,y> <x,y>  the route between two points crossing the fewest vent lines
+    day06 population <days> [modulus]
+                            the number of lanternfish after any number of days
+    day06 csv <days>        the number of lanternfish with each timer every day, as CSV
+    day06 growth            the long run daily growth factor and when the school outgrows a u64";
+
+/// Most digits `day06 population` computes the exact number of fish for, beyond this it takes too
+/// long and the modulus form is needed.
+const MAX_POPULATION_DIGITS: f64 = 10_000.0;
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args[..] {
+        ["day03", "report"] => {
+            let input = include_str!("../../inputs/day03.txt");
+            let readings: Readings = input.parse()?;
+            print!("{}", readings.report());
+        }
+        ["day04", "replay", board] => {
+            let input = include_str!("../../inputs/day04.txt");
+            let bingo: Bingo = input.parse()?;
+            replay_board(&bingo, board.parse()?)?;
+        }
+        ["day04", "fastest", board, goal] => {
+            let input = include_str!("../../inputs/day04.txt");
+            let bingo: Bingo = input.parse()?;
+            let board: usize = board.parse()?;
+            if board >= bingo.boards.len() {
+                bail!(
+                    "board {} out of range, there are {}",
+                    board,
+                    bingo.boards.len()
+                );
+            }
+            let goal = match goal {
+                "first" => Goal::First,
+                "last" => Goal::Last,
+                _ => bail!(USAGE),
+            };
+
+            match bingo.fastest_win(board, goal) {
+                SearchResult::Found { sequence, optimal } => {
+                    println!("{}", sequence);
+                    if !optimal {
+                        eprintln!("search budget exhausted, a shorter sequence may exist");
+                    }
+                }
+                SearchResult::Impossible => println!("impossible"),
+                SearchResult::Exhausted => println!("search budget exhausted"),
+            }
+        }
+        ["day04", "simulate", trials, seed] => {
+            let input = include_str!("../../inputs/day04.txt");
+            let bingo: Bingo = input.parse()?;
+            let simulation = bingo.simulate(trials.parse()?, seed.parse()?);
+
+            println!(
+                "{:>5} {:>7} {:>7} {:>7} {:>9}",
+                "board", "first", "last", "wins", "mean turn"
+            );
+            for (i, stats) in simulation.boards.iter().enumerate() {
+                println!(
+                    "{:>5} {:>7.4} {:>7.4} {:>7.4} {:>9}",
+                    i,
+                    stats.first,
+                    stats.last,
+                    stats.wins,
+                    stats
+                        .mean_turn
+                        .map_or_else(|| "-".to_string(), |turn| format!("{:.2}", turn))
+                );
+            }
+        }
+        ["day05", "grid"] => {
+            let lines = day05_lines()?;
+            print!("{}", lines.overlap_grid(Raster::Lattice)?);
+        }
+        ["day05", "heatmap", path] => {
+            let grid = day05_lines()?.overlap_grid(Raster::Lattice)?;
+            let image = if path.ends_with(".pgm") {
+                grid.to_pgm()
+            } else {
+                grid.to_ppm()
+            };
+            std::fs::write(path, image)?;
+        }
+        ["day05", "at", point] => {
+            let lines = day05_lines()?;
+            let point: Point = point.parse()?;
+            for line in lines.spatial_index(Raster::Lattice).at(point) {
+                println!("{}", line);
+            }
+        }
+        ["day05", "within", corner, opposite] => {
+            let lines = day05_lines()?;
+            let index = lines.spatial_index(Raster::Lattice);
+            for line in index.within(corner.parse()?, opposite.parse()?) {
+                println!("{}", line);
+            }
+        }
+        ["day05", "path", from, to] => {
+            let grid = day05_lines()?.overlap_grid(Raster::Lattice)?;
+            let path = grid
+                .safest_path(from.parse()?, to.parse()?)
+                .ok_or_else(|| anyhow!("both points must be within the vents"))?;
+            println!("crosses {} lines", path.danger);
+            for cell in path.cells {
+                println!("{}", cell);
+            }
+        }
+        ["day06", "population", days] => {
+            let input = include_str!("../../inputs/day06.txt");
+            let school: NonNaiveSchool = input.parse()?;
+            let days: u64 = days.parse()?;
+            let digits = days as f64 * school.species().growth_rate().log10();
+            if digits > MAX_POPULATION_DIGITS {
+                bail!(
+                    "the number of fish after {} days has about {:.0} digits, too many to compute \
+                     exactly, use `day06 population <days> <modulus>` instead",
+                    days,
+                    digits
+                );
+            }
+            println!("{}", school.population_after(days));
+        }
+        ["day06", "population", days, modulus] => {
+            let input = include_str!("../../inputs/day06.txt");
+            let school: NonNaiveSchool = input.parse()?;
+            let modulus: u64 = modulus.parse()?;
+            if modulus == 0 {
+                bail!("modulus must be positive");
+            }
+            println!("{}", school.population_after_mod(days.parse()?, modulus));
+        }
+        ["day06", "csv", days] => {
+            let input = include_str!("../../inputs/day06.txt");
+            let school: NonNaiveSchool = input.parse()?;
+            print!("{}", school.to_csv(days.parse()?));
+        }
+        ["day06", "growth"] => {
+            let input = include_str!("../../inputs/day06.txt");
+            let school: NonNaiveSchool = input.parse()?;
+            println!("growth rate: {:.6} per day", school.species().growth_rate());
+            if let Some(last) = school.days().last() {
+                println!("last day within a u64: {} ({} fish)", last.day, last.total);
+            }
+        }
+        _ => bail!(USAGE),
+    }
+
+    Ok(())
+}
+
+fn replay_board(bingo: &Bingo, board: usize) -> anyhow::Result<()> {
+    if board >= bingo.boards.len() {
+        bail!(
+            "board {} out of range, there are {}",
+            board,
+            bingo.boards.len()
+        );
+    }
+
+    let mut replay = bingo.replay();
+    let mut turn = 0;
+    while let Some(event) = replay.step() {
+        match *event {
+            Event::Draw { turn: t, .. } => turn = t,
+            Event::Marked { board: b, .. } if b == board => {
+                let draw = replay
+                    .draw()
+                    .ok_or_else(|| anyhow!("marked before a draw"))?;
+                println!("turn {}: called {}", turn, draw);
+                print!("{}", replay.boards()[board].render(Highlight::Ansi));
+                println!();
+            }
+            Event::Won {
+                board: b, score, ..
+            } if b == board => {
+                println!("won on turn {} with a score of {}", turn, score);
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    println!("never won");
+    Ok(())
+}
+
+fn day05_lines() -> anyhow::Result<Lines> {
+    let input = include_str!("../../inputs/day05.txt");
+    let lines: Vec<Line> = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| line.parse().map_err(|e| anyhow!("line {}: {}", i + 1, e)))
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(lines.into())
+}
@@ -2,11 +2,7 @@ use aoc2021::day03::Readings;
 
 fn main() -> anyhow::Result<()> {
     let input = include_str!("../../inputs/day03.txt");
-    let readings: Readings<12> = input
-        .lines()
-        .map(str::parse)
-        .collect::<anyhow::Result<Vec<_>>>()?
-        .into();
+    let readings: Readings = input.parse()?;
 
     let (gamma, epsilon) = readings.power_consumption();
     println!("Part 1: {}", gamma * epsilon);
@@ -3,10 +3,11 @@ use aoc2021::day04::Bingo;
 fn main() -> anyhow::Result<()> {
     let input = include_str!("../../inputs/day04.txt");
 
-    let mut bingo_game: Bingo<5, 5> = input.parse()?;
+    let bingo_game: Bingo = input.parse()?;
+    let ranking = bingo_game.ranking();
 
-    println!("part 1: {}", bingo_game.play_game());
-    println!("part 2: {}", bingo_game.play_until_last());
+    println!("part 1: {}", ranking.first().map_or(0, |win| win.score));
+    println!("part 2: {}", ranking.last().map_or(0, |win| win.score));
 
     Ok(())
 }
@@ -8,7 +8,10 @@ fn main() -> anyhow::Result<()> {
         .collect::<anyhow::Result<Vec<Line>>>()?
         .into();
 
-    println!("Part 1: {}", lines.dangerous_points_horizontal_or_vert());
-    println!("Part 2: {}", lines.dangerous_points_all());
+    println!(
+        "Part 1: {}",
+        lines.dangerous_points_horizontal_or_vert_analytic()
+    );
+    println!("Part 2: {}", lines.dangerous_points_all_analytic());
     Ok(())
 }
@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use aoc2021::day07::Crabs;
 
 fn main() -> anyhow::Result<()> {
@@ -5,10 +6,14 @@ fn main() -> anyhow::Result<()> {
 
     let crabs: Crabs = input.parse()?;
 
-    let part_1 = crabs.find_best_position().unwrap();
-    println!("Part 1: {}", part_1);
+    let part_1 = crabs
+        .best_alignment()
+        .ok_or_else(|| anyhow!("fuel overflows"))?;
+    println!("Part 1: {}", part_1.fuel);
 
-    let part_2 = crabs.find_best_position_alt().unwrap();
-    println!("Part 2: {}", part_2);
+    let part_2 = crabs
+        .best_alignment_alt()
+        .ok_or_else(|| anyhow!("fuel overflows"))?;
+    println!("Part 2: {}", part_2.fuel);
     Ok(())
 }
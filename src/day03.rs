@@ -1,182 +1,512 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 
-pub struct Readings<const READING_LENGTH: usize>(Vec<Reading<READING_LENGTH>>);
+/// The widest reading that fits in the `u64` backing a `Reading`.
+pub const MAX_WIDTH: usize = u64::BITS as usize;
 
-const fn masks<const LENGTH: usize>() -> [u32; LENGTH] {
-    let mut masks = [0u32; LENGTH];
-    let mut i = 0;
-    while i < LENGTH {
-        masks[i] = 1 << (LENGTH - 1 - i);
-        i += 1;
-    }
-
-    masks
+#[derive(Clone, Debug)]
+pub struct Readings {
+    readings: Vec<Reading>,
+    width: usize,
 }
 
-impl<const READING_LENGTH: usize> Readings<READING_LENGTH> {
-    pub fn power_consumption(&self) -> (u32, u32) {
-        let masks = masks::<READING_LENGTH>();
-        // count number of 1s in each position
-        let counts = self
-            .0
+impl Readings {
+    /// Number of bits in every reading, detected from the input.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of 1s in each bit position, most significant first.
+    pub fn column_counts(&self) -> Vec<u64> {
+        self.readings
             .iter()
-            .fold([0u32; READING_LENGTH], |mut count, reading| {
-                for i in 0..READING_LENGTH {
-                    count[i] += (masks[i] & reading.0) >> (READING_LENGTH - 1 - i);
+            .fold(vec![0u64; self.width], |mut count, reading| {
+                for (pos, count) in count.iter_mut().enumerate() {
+                    *count += reading.bit(pos);
                 }
                 count
-            });
-
-        let half_len = (self.0.len() / 2) as u32;
-        let mut bits = [0u32; READING_LENGTH];
-        for (i, &count) in counts.iter().enumerate() {
-            // if there are more 1s than 0s then set position to 1
-            if count > half_len {
-                bits[i] = 1;
-            }
-        }
-
-        let gamma = bits
-            .iter()
-            .rev()
-            .enumerate()
-            .fold(0u32, |gamma, (i, &bit)| gamma + (bit << i));
+            })
+    }
 
-        // flip bits to find epsilon and set bits for > 2^4 back to 0
-        let epsilon = !gamma & ((1 << READING_LENGTH) - 1);
+    pub fn power_consumption(&self) -> (u64, u64) {
+        // count number of 1s in each position
+        gamma_epsilon(&self.column_counts(), self.readings.len(), self.width)
+    }
 
-        (gamma, epsilon)
+    /// Product of the oxygen generator and CO2 scrubber ratings, a `u128` as readings can be up to
+    /// 64 bits wide.
+    pub fn life_support_rating(&self) -> u128 {
+        let o2 = self
+            .filter(BitCriteria::OXYGEN_GENERATOR)
+            .expect("no unique oxygen generator rating");
+        let co2 = self
+            .filter(BitCriteria::CO2_SCRUBBER)
+            .expect("no unique CO2 scrubber rating");
+
+        o2.reading.value as u128 * co2.reading.value as u128
     }
 
-    pub fn life_support_rating(&self) -> u32 {
-        self.o2().0 * self.co2().0
+    /// Repeatedly keeps only the readings whose bit at the current position matches `criteria`,
+    /// moving one position to the right each round, until a single reading is left.
+    ///
+    /// Returns None if every reading is eliminated or the bits run out with more than one reading
+    /// remaining (i.e. there are duplicates).
+    pub fn filter(&self, criteria: BitCriteria) -> Option<Filtered> {
+        let (readings, survivors) = self.filter_trace(criteria);
+
+        match readings[..] {
+            [reading] => Some(Filtered {
+                reading,
+                rounds: survivors.len(),
+            }),
+            _ => None,
+        }
     }
 
-    fn o2(&self) -> Reading<READING_LENGTH> {
-        let mut readings: Vec<Reading<READING_LENGTH>> = self.0.to_vec();
-        let mut temp: Vec<Reading<READING_LENGTH>> = Vec::new();
+    /// Runs the filter, returning the readings left at the end along with the number of readings
+    /// that survived each round.
+    fn filter_trace(&self, criteria: BitCriteria) -> (Vec<Reading>, Vec<usize>) {
+        let mut readings: Vec<Reading> = self.readings.to_vec();
+        let mut temp: Vec<Reading> = Vec::new();
+        let mut survivors: Vec<usize> = Vec::new();
         let mut pos: usize = 0;
 
-        while readings.len() > 1 {
-            let bit = Self::most_common_bit(&readings, pos);
+        while readings.len() > 1 && pos < self.width {
+            let ones = readings
+                .iter()
+                .fold(0, |count, reading| count + reading.bit(pos));
+            let bit = criteria.select(ones, readings.len() as u64);
             for reading in readings.iter().copied() {
-                if (reading.0 >> (READING_LENGTH - 1 - pos) & 1) == bit {
+                if reading.bit(pos) == bit {
                     temp.push(reading);
                 }
             }
             pos += 1;
             std::mem::swap(&mut temp, &mut readings);
             temp.clear();
+            survivors.push(readings.len());
         }
 
-        readings[0]
+        (readings, survivors)
     }
 
-    fn co2(&self) -> Reading<READING_LENGTH> {
-        let mut readings: Vec<Reading<READING_LENGTH>> = self.0.to_vec();
-        let mut temp: Vec<Reading<READING_LENGTH>> = Vec::new();
-        let mut pos: usize = 0;
+    /// Same as `life_support_rating`, but sorts the readings once and then narrows a range of the
+    /// sorted readings for each bit position instead of copying the survivors every round.
+    pub fn life_support_rating_partitioned(&self) -> u128 {
+        let mut sorted = self.readings.to_vec();
+        sorted.sort_unstable_by_key(|reading| reading.value);
 
-        while readings.len() > 1 {
-            let bit = Self::least_common_bit(&readings, pos);
-            for reading in readings.iter().copied() {
-                if (reading.0 >> (READING_LENGTH - 1 - pos) & 1) == bit {
-                    temp.push(reading);
-                }
-            }
-            pos += 1;
-            std::mem::swap(&mut temp, &mut readings);
-            temp.clear();
+        let o2 = filter_sorted(&sorted, self.width, BitCriteria::OXYGEN_GENERATOR)
+            .expect("no unique oxygen generator rating");
+        let co2 = filter_sorted(&sorted, self.width, BitCriteria::CO2_SCRUBBER)
+            .expect("no unique CO2 scrubber rating");
+
+        o2.reading.value as u128 * co2.reading.value as u128
+    }
+
+    pub fn bit_sliced(&self) -> BitSlicedReadings {
+        BitSlicedReadings::from(self)
+    }
+
+    pub fn report(&self) -> Report {
+        let total = self.readings.len() as u64;
+        let columns = self
+            .column_counts()
+            .into_iter()
+            .map(|ones| ColumnStats {
+                ones,
+                zeros: total - ones,
+            })
+            .collect();
+
+        Report {
+            readings: self.readings.len(),
+            columns,
+            o2_survivors: self.filter_trace(BitCriteria::OXYGEN_GENERATOR).1,
+            co2_survivors: self.filter_trace(BitCriteria::CO2_SCRUBBER).1,
         }
+    }
+}
 
-        readings[0]
+/// Which bit value survives a round of filtering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Commonality {
+    /// Keep readings with the majority bit in the current position.
+    Most,
+    /// Keep readings with the minority bit in the current position.
+    Least,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitCriteria {
+    pub keep: Commonality,
+    /// Bit kept when 0s and 1s are equally common, `true` for 1.
+    pub tie: bool,
+}
+
+impl BitCriteria {
+    /// Most common bit, 1 on a tie.
+    pub const OXYGEN_GENERATOR: Self = Self::new(Commonality::Most, true);
+    /// Least common bit, 0 on a tie.
+    pub const CO2_SCRUBBER: Self = Self::new(Commonality::Least, false);
+
+    pub const fn new(keep: Commonality, tie: bool) -> Self {
+        Self { keep, tie }
     }
 
-    fn most_common_bit(readings: &[Reading<READING_LENGTH>], pos: usize) -> u32 {
-        let masks = masks::<READING_LENGTH>();
-        let count = readings.iter().copied().fold(0, |count, reading| {
-            count + ((masks[pos] & reading.0) >> (READING_LENGTH - 1 - pos))
-        });
+    /// Returns the bit to keep given the number of 1s out of `len` readings.
+    fn select(&self, ones: u64, len: u64) -> u64 {
+        let zeros = len - ones;
+        let bit = match ones.cmp(&zeros) {
+            Ordering::Equal => return self.tie as u64,
+            Ordering::Greater => 1,
+            Ordering::Less => 0,
+        };
+
+        match self.keep {
+            Commonality::Most => bit,
+            Commonality::Least => bit ^ 1,
+        }
+    }
+}
 
-        let half_len = (readings.len() / 2) as u32;
+/// The reading left after filtering and the number of rounds it took to get there.
+#[derive(Clone, Copy, Debug)]
+pub struct Filtered {
+    pub reading: Reading,
+    pub rounds: usize,
+}
 
-        match readings.len() % 2 {
-            0 => {
-                if count >= half_len {
-                    1
-                } else {
-                    0
-                }
-            }
-            1 => {
-                if count > half_len {
-                    1
-                } else {
-                    0
-                }
-            }
-            _ => unreachable!(),
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnStats {
+    pub ones: u64,
+    pub zeros: u64,
+}
+
+impl ColumnStats {
+    /// Fraction of readings with a 1 in this position.
+    pub fn ratio(&self) -> f64 {
+        self.ones as f64 / (self.ones + self.zeros) as f64
+    }
+}
+
+/// Per-position bit counts along with the number of readings left after each round of the oxygen
+/// generator and CO2 scrubber filters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub readings: usize,
+    pub columns: Vec<ColumnStats>,
+    pub o2_survivors: Vec<usize>,
+    pub co2_survivors: Vec<usize>,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:>5} {:>10} {:>10} {:>7}",
+            "bit", "ones", "zeros", "ratio"
+        )?;
+        for (pos, column) in self.columns.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>5} {:>10} {:>10} {:>7.3}",
+                pos,
+                column.ones,
+                column.zeros,
+                column.ratio()
+            )?;
         }
+
+        writeln!(f)?;
+        writeln!(f, "{:>5} {:>10} {:>10}", "round", "o2", "co2")?;
+        writeln!(f, "{:>5} {:>10} {:>10}", 0, self.readings, self.readings)?;
+        let rounds = self.o2_survivors.len().max(self.co2_survivors.len());
+        for round in 0..rounds {
+            let cell = |survivors: &[usize]| {
+                survivors
+                    .get(round)
+                    .map_or_else(|| "-".to_string(), usize::to_string)
+            };
+            writeln!(
+                f,
+                "{:>5} {:>10} {:>10}",
+                round + 1,
+                cell(&self.o2_survivors),
+                cell(&self.co2_survivors)
+            )?;
+        }
+
+        Ok(())
     }
+}
 
-    fn least_common_bit(readings: &[Reading<READING_LENGTH>], pos: usize) -> u32 {
-        let masks = masks::<READING_LENGTH>();
-        let count = readings.iter().copied().fold(0, |count, reading| {
-            count + ((masks[pos] & reading.0) >> (READING_LENGTH - 1 - pos))
-        });
+/// Filters readings sorted by value. The readings left after each round share all the bits
+/// filtered on so far, so within them the ones with a 0 in the next position all come before the
+/// ones with a 1, and the split can be found with a binary search.
+fn filter_sorted(sorted: &[Reading], width: usize, criteria: BitCriteria) -> Option<Filtered> {
+    let mut range = 0..sorted.len();
+    let mut pos: usize = 0;
+
+    while range.len() > 1 && pos < width {
+        let split = range.start + sorted[range.clone()].partition_point(|r| r.bit(pos) == 0);
+        let ones = (range.end - split) as u64;
+
+        if criteria.select(ones, range.len() as u64) == 1 {
+            range.start = split;
+        } else {
+            range.end = split;
+        }
+        pos += 1;
+    }
 
-        let half_len = (readings.len() / 2) as u32;
+    match sorted[range] {
+        [reading] => Some(Filtered {
+            reading,
+            rounds: pos,
+        }),
+        _ => None,
+    }
+}
 
-        match readings.len() % 2 {
-            0 => {
-                if count >= half_len {
-                    0
-                } else {
-                    1
-                }
-            }
-            1 => {
-                if count > half_len {
-                    0
-                } else {
-                    1
-                }
-            }
-            _ => unreachable!(),
+/// Sets each bit of gamma to the majority bit for that position and returns it along with epsilon.
+fn gamma_epsilon(counts: &[u64], len: usize, width: usize) -> (u64, u64) {
+    let half_len = (len / 2) as u64;
+
+    // if there are more 1s than 0s then set position to 1
+    let gamma = counts.iter().fold(0u64, |gamma, &count| {
+        (gamma << 1) | (count > half_len) as u64
+    });
+
+    // flip bits to find epsilon and set bits above the reading width back to 0
+    let epsilon = !gamma & mask(width);
+
+    (gamma, epsilon)
+}
+
+/// Mask covering the lowest `width` bits.
+fn mask(width: usize) -> u64 {
+    u64::MAX >> (MAX_WIDTH - width)
+}
+
+impl TryFrom<Vec<Reading>> for Readings {
+    type Error = anyhow::Error;
+
+    /// Takes the width from the first reading and checks that every other reading matches it.
+    fn try_from(readings: Vec<Reading>) -> anyhow::Result<Self> {
+        let width = match readings.first() {
+            Some(reading) => reading.width,
+            None => bail!("No readings"),
+        };
+
+        if let Some((i, reading)) = readings
+            .iter()
+            .enumerate()
+            .find(|(_, reading)| reading.width != width)
+        {
+            bail!(
+                "Reading {} has width {}, expected {}",
+                i + 1,
+                reading.width,
+                width
+            );
         }
+
+        Ok(Self { readings, width })
     }
 }
 
-impl<const READING_LENGTH: usize> From<Vec<Reading<READING_LENGTH>>> for Readings<READING_LENGTH> {
-    fn from(readings: Vec<Reading<READING_LENGTH>>) -> Self {
-        Self(readings)
+impl FromStr for Readings {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        s.lines()
+            .enumerate()
+            .map(|(i, line)| {
+                line.parse()
+                    .map_err(|e: anyhow::Error| anyhow!("line {}: {}", i + 1, e))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .try_into()
     }
 }
 
 #[derive(Clone, Copy, Debug)]
-pub struct Reading<const LENGTH: usize>(u32);
+pub struct Reading {
+    value: u64,
+    width: usize,
+}
 
-impl<const LENGTH: usize> FromStr for Reading<LENGTH> {
+impl Reading {
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the bit at `pos`, counting from the most significant bit of the reading.
+    fn bit(&self, pos: usize) -> u64 {
+        (self.value >> (self.width - 1 - pos)) & 1
+    }
+}
+
+impl FromStr for Reading {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> anyhow::Result<Self> {
-        if s.len() != LENGTH {
+        if s.is_empty() || s.len() > MAX_WIDTH {
             bail!("Incorrect input length")
         }
 
-        let reading: u32 = s
-            .chars()
-            .rev()
-            .enumerate()
-            .try_fold(0, |reading, (i, c)| match c {
-                '0' => Ok(reading),
-                '1' => Ok(reading + (1 << i)),
-                _ => bail!("invalid input"),
-            })?;
+        let value: u64 = s.chars().try_fold(0, |reading, c| match c {
+            '0' => Ok(reading << 1),
+            '1' => Ok((reading << 1) | 1),
+            _ => bail!("invalid input"),
+        })?;
 
-        Ok(Self(reading))
+        Ok(Self {
+            value,
+            width: s.len(),
+        })
+    }
+}
+
+/// Transposed readings: one bitset per position, where bit `i` of a column is the bit of reading
+/// `i` in that position. Counting a column is a popcount over its words and filtering a round is a
+/// bitwise AND of the surviving set with the column (or its complement), so no readings are copied.
+#[derive(Clone, Debug)]
+pub struct BitSlicedReadings {
+    columns: Vec<Vec<u64>>,
+    len: usize,
+    width: usize,
+}
+
+impl BitSlicedReadings {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn column_counts(&self) -> Vec<u64> {
+        self.columns
+            .iter()
+            .map(|column| popcount(column.iter().copied()))
+            .collect()
+    }
+
+    pub fn power_consumption(&self) -> (u64, u64) {
+        gamma_epsilon(&self.column_counts(), self.len, self.width)
+    }
+
+    pub fn life_support_rating(&self) -> u128 {
+        let o2 = self
+            .filter(BitCriteria::OXYGEN_GENERATOR)
+            .expect("no unique oxygen generator rating");
+        let co2 = self
+            .filter(BitCriteria::CO2_SCRUBBER)
+            .expect("no unique CO2 scrubber rating");
+
+        o2.reading.value as u128 * co2.reading.value as u128
+    }
+
+    /// Same as `Readings::filter`.
+    pub fn filter(&self, criteria: BitCriteria) -> Option<Filtered> {
+        let mut alive = self.all();
+        let mut remaining = self.len as u64;
+        let mut pos: usize = 0;
+
+        while remaining > 1 && pos < self.width {
+            let column = &self.columns[pos];
+            let ones = popcount(alive.iter().zip(column).map(|(alive, bits)| alive & bits));
+
+            if criteria.select(ones, remaining) == 1 {
+                alive
+                    .iter_mut()
+                    .zip(column)
+                    .for_each(|(alive, bits)| *alive &= bits);
+                remaining = ones;
+            } else {
+                alive
+                    .iter_mut()
+                    .zip(column)
+                    .for_each(|(alive, bits)| *alive &= !bits);
+                remaining -= ones;
+            }
+            pos += 1;
+        }
+
+        if remaining != 1 {
+            return None;
+        }
+
+        let (word, bits) = alive.iter().enumerate().find(|(_, bits)| **bits != 0)?;
+        let index = word * WORD_BITS + bits.trailing_zeros() as usize;
+
+        Some(Filtered {
+            reading: self.reading(index),
+            rounds: pos,
+        })
+    }
+
+    /// Reassembles the reading at `index` from the columns.
+    fn reading(&self, index: usize) -> Reading {
+        let (word, bit) = (index / WORD_BITS, index % WORD_BITS);
+        let value = self.columns.iter().fold(0, |value, column| {
+            (value << 1) | ((column[word] >> bit) & 1)
+        });
+
+        Reading {
+            value,
+            width: self.width,
+        }
+    }
+
+    /// Bitset with a 1 for every reading.
+    fn all(&self) -> Vec<u64> {
+        let (full, rest) = (self.len / WORD_BITS, self.len % WORD_BITS);
+        let mut all = vec![u64::MAX; full];
+        if rest != 0 {
+            all.push(mask(rest));
+        }
+        all
+    }
+}
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+fn popcount(words: impl Iterator<Item = u64>) -> u64 {
+    words.map(|word| word.count_ones() as u64).sum()
+}
+
+impl From<&Readings> for BitSlicedReadings {
+    fn from(readings: &Readings) -> Self {
+        let words = readings.readings.len().div_ceil(WORD_BITS);
+        let mut columns = vec![vec![0u64; words]; readings.width];
+
+        for (i, reading) in readings.readings.iter().enumerate() {
+            let (word, bit) = (i / WORD_BITS, i % WORD_BITS);
+            for (pos, column) in columns.iter_mut().enumerate() {
+                column[word] |= reading.bit(pos) << bit;
+            }
+        }
+
+        Self {
+            columns,
+            len: readings.readings.len(),
+            width: readings.width,
+        }
     }
 }
 
@@ -195,38 +525,81 @@ mod tests {
         #[test_case("10000", 16; "Should parse to 16")]
         #[test_case("10101", 21; "Should parse to 21")]
         #[test_case("11111", 31; "Should parse to 31")]
-        fn parse_reading(input: &str, expected: u32) {
-            let reading: Reading<5> = input.parse().unwrap();
-            assert_eq!(reading.0, expected);
+        fn parse_reading(input: &str, expected: u64) {
+            let reading: Reading = input.parse().unwrap();
+            assert_eq!(reading.value, expected);
+            assert_eq!(reading.width, 5);
+        }
+
+        #[test]
+        fn parse_wide_reading() {
+            let input = "1".repeat(64);
+            let reading: Reading = input.parse().unwrap();
+            assert_eq!(reading.value, u64::MAX);
+            assert_eq!(reading.width, 64);
         }
 
         #[test]
         #[should_panic]
         fn errors_on_invalid_length() {
-            let _reading: Reading<5> = "01".parse().unwrap();
+            let _reading: Reading = "".parse().unwrap();
+        }
+
+        #[test]
+        #[should_panic]
+        fn errors_on_too_wide() {
+            let _reading: Reading = "1".repeat(65).parse().unwrap();
         }
 
         #[test]
         #[should_panic]
         fn errors_on_invalid_character() {
-            let _reading: Reading<5> = "20101".parse().unwrap();
+            let _reading: Reading = "20101".parse().unwrap();
         }
     }
 
     mod readings {
-        use super::Readings;
+        use rand::{Rng, SeedableRng};
+        use rand_chacha::ChaCha8Rng;
+
+        use super::{filter_sorted, BitCriteria, Commonality, Readings};
+
+        const EXAMPLE: &str = "\
+00100
+11110
+10110
+10111
+10101
+01111
+00111
+11100
+10000
+11001
+00010
+01010";
+
+        #[test]
+        fn detects_width() {
+            let readings: Readings = EXAMPLE.parse().unwrap();
+
+            assert_eq!(readings.width(), 5);
+        }
+
+        #[test]
+        #[should_panic]
+        fn errors_on_inconsistent_width() {
+            let _readings: Readings = "00100\n0111\n10110".parse().unwrap();
+        }
+
+        #[test]
+        #[should_panic]
+        fn errors_on_empty_input() {
+            let _readings: Readings = "".parse().unwrap();
+        }
 
         #[test]
         fn calculate_power_consumption() {
-            let readings: Readings<5> = vec![
-                "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
-                "11001", "00010", "01010",
-            ]
-            .into_iter()
-            .map(|reading| reading.parse())
-            .collect::<anyhow::Result<Vec<_>>>()
-            .unwrap()
-            .into();
+            let readings: Readings = EXAMPLE.parse().unwrap();
 
             let (gamma_rate, epsilon_rate) = readings.power_consumption();
 
@@ -235,19 +608,177 @@ mod tests {
         }
 
         #[test]
-        fn calculate_life_support() {
-            let readings: Readings<5> = vec![
-                "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
-                "11001", "00010", "01010",
+        fn calculate_power_consumption_wide() {
+            let readings: Readings = ["1".repeat(40), "1".repeat(40), "0".repeat(40)]
+                .join("\n")
+                .parse()
+                .unwrap();
+
+            let (gamma_rate, epsilon_rate) = readings.power_consumption();
+
+            assert_eq!(gamma_rate, (1 << 40) - 1);
+            assert_eq!(epsilon_rate, 0);
+        }
+
+        #[test]
+        fn calculate_life_support_wide() {
+            let readings: Readings = [
+                "1".repeat(40),
+                format!("1{}", "0".repeat(39)),
+                format!("0{}", "1".repeat(39)),
             ]
-            .into_iter()
-            .map(|reading| reading.parse())
-            .collect::<anyhow::Result<Vec<_>>>()
-            .unwrap()
-            .into();
+            .join("\n")
+            .parse()
+            .unwrap();
+            // more than fits in a u64
+            let expected = ((1u128 << 40) - 1) * ((1 << 39) - 1);
+
+            assert_eq!(readings.life_support_rating(), expected);
+            assert_eq!(readings.life_support_rating_partitioned(), expected);
+            assert_eq!(readings.bit_sliced().life_support_rating(), expected);
+        }
+
+        #[test]
+        fn calculate_life_support() {
+            let readings: Readings = EXAMPLE.parse().unwrap();
 
             let life_support = readings.life_support_rating();
             assert_eq!(life_support, 230);
         }
+
+        #[test]
+        fn report() {
+            let readings: Readings = EXAMPLE.parse().unwrap();
+            let report = readings.report();
+
+            let ones: Vec<u64> = report.columns.iter().map(|column| column.ones).collect();
+            let zeros: Vec<u64> = report.columns.iter().map(|column| column.zeros).collect();
+            assert_eq!(ones, vec![7, 5, 8, 7, 5]);
+            assert_eq!(zeros, vec![5, 7, 4, 5, 7]);
+            assert_eq!(report.columns[2].ratio(), 8.0 / 12.0);
+            assert_eq!(report.o2_survivors, vec![7, 4, 3, 2, 1]);
+            assert_eq!(report.co2_survivors, vec![5, 2, 1]);
+        }
+
+        #[test]
+        fn bit_sliced_matches_readings() {
+            let readings: Readings = EXAMPLE.parse().unwrap();
+            let bit_sliced = readings.bit_sliced();
+
+            assert_eq!(bit_sliced.column_counts(), readings.column_counts());
+            assert_eq!(bit_sliced.power_consumption(), (22, 9));
+            assert_eq!(bit_sliced.life_support_rating(), 230);
+
+            let o2 = bit_sliced.filter(BitCriteria::OXYGEN_GENERATOR).unwrap();
+            assert_eq!(o2.reading.value(), 23);
+            assert_eq!(o2.rounds, 5);
+        }
+
+        #[test]
+        fn bit_sliced_matches_readings_across_words() {
+            // 200 readings so the columns span several words
+            let mut rng = ChaCha8Rng::seed_from_u64(3);
+            let input = (0..200)
+                .map(|_| format!("{:020b}", rng.gen_range(0..1u32 << 20)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let readings: Readings = input.parse().unwrap();
+            let bit_sliced = readings.bit_sliced();
+
+            assert_eq!(bit_sliced.column_counts(), readings.column_counts());
+            assert_eq!(bit_sliced.power_consumption(), readings.power_consumption());
+            for criteria in [
+                BitCriteria::OXYGEN_GENERATOR,
+                BitCriteria::CO2_SCRUBBER,
+                BitCriteria::new(Commonality::Most, false),
+                BitCriteria::new(Commonality::Least, true),
+            ] {
+                let expected = readings
+                    .filter(criteria)
+                    .map(|f| (f.reading.value(), f.rounds));
+                let actual = bit_sliced
+                    .filter(criteria)
+                    .map(|f| (f.reading.value(), f.rounds));
+                assert_eq!(actual, expected);
+            }
+        }
+
+        #[test]
+        fn partitioned_matches_readings() {
+            let readings: Readings = EXAMPLE.parse().unwrap();
+
+            assert_eq!(readings.life_support_rating_partitioned(), 230);
+        }
+
+        #[test]
+        fn filter_sorted_matches_filter() {
+            let mut rng = ChaCha8Rng::seed_from_u64(30);
+            let input = (0..500)
+                .map(|_| format!("{:016b}", rng.gen::<u16>()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let readings: Readings = input.parse().unwrap();
+            let mut sorted = readings.readings.clone();
+            sorted.sort_unstable_by_key(|reading| reading.value);
+
+            for criteria in [
+                BitCriteria::OXYGEN_GENERATOR,
+                BitCriteria::CO2_SCRUBBER,
+                BitCriteria::new(Commonality::Most, false),
+                BitCriteria::new(Commonality::Least, true),
+            ] {
+                let expected = readings
+                    .filter(criteria)
+                    .map(|f| (f.reading.value(), f.rounds));
+                let actual = filter_sorted(&sorted, readings.width(), criteria)
+                    .map(|f| (f.reading.value(), f.rounds));
+                assert_eq!(actual, expected);
+            }
+            assert_eq!(
+                readings.life_support_rating_partitioned(),
+                readings.life_support_rating()
+            );
+        }
+
+        #[test]
+        fn filter_oxygen_generator() {
+            let readings: Readings = EXAMPLE.parse().unwrap();
+
+            let o2 = readings.filter(BitCriteria::OXYGEN_GENERATOR).unwrap();
+            assert_eq!(o2.reading.value(), 23);
+            assert_eq!(o2.rounds, 5);
+        }
+
+        #[test]
+        fn filter_co2_scrubber() {
+            let readings: Readings = EXAMPLE.parse().unwrap();
+
+            let co2 = readings.filter(BitCriteria::CO2_SCRUBBER).unwrap();
+            assert_eq!(co2.reading.value(), 10);
+            assert_eq!(co2.rounds, 3);
+        }
+
+        #[test]
+        fn filter_uses_tie_value() {
+            let readings: Readings = "10\n01".parse().unwrap();
+
+            let ones = readings
+                .filter(BitCriteria::new(Commonality::Least, true))
+                .unwrap();
+            let zeros = readings
+                .filter(BitCriteria::new(Commonality::Most, false))
+                .unwrap();
+
+            assert_eq!(ones.reading.value(), 2);
+            assert_eq!(zeros.reading.value(), 1);
+            assert_eq!(ones.rounds, 1);
+        }
+
+        #[test]
+        fn filter_returns_none_for_duplicates() {
+            let readings: Readings = "101\n101".parse().unwrap();
+
+            assert!(readings.filter(BitCriteria::OXYGEN_GENERATOR).is_none());
+        }
     }
 }
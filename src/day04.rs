@@ -1,12 +1,153 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::hash::{BuildHasherDefault, Hasher};
 use std::ops::Index;
 use std::str::FromStr;
+use std::sync::Arc;
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
 
+/// A set of cells which, once all called, wins the board. A board wins when any of the cells
+/// described by its rules are complete.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Board<const WIDTH: usize, const HEIGHT: usize> {
-    inner: [[(u8, Status); WIDTH]; HEIGHT],
-    has_won: bool,
+pub enum WinRule {
+    /// Any complete row.
+    Rows,
+    /// Any complete column.
+    Columns,
+    /// Either of the two diagonals, only valid for square boards.
+    Diagonals,
+    /// The four corner cells.
+    Corners,
+    /// Every cell on the board.
+    Blackout,
+    /// A custom set of `(x, y)` cells.
+    Pattern(Vec<(usize, usize)>),
+}
+
+impl WinRule {
+    /// Rows and columns, as in the puzzle.
+    pub const LINES: [WinRule; 2] = [WinRule::Rows, WinRule::Columns];
+}
+
+/// Win rules expanded into the sets of cells they describe, shared between all the boards in a
+/// game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Patterns {
+    /// Cells (indexed by `y * width + x`) in each pattern.
+    cells: Vec<Vec<usize>>,
+    /// Indices of the patterns each cell (indexed by `y * width + x`) belongs to.
+    by_cell: Vec<Vec<usize>>,
+}
+
+impl Patterns {
+    fn new(rules: &[WinRule], width: usize, height: usize) -> anyhow::Result<Self> {
+        if rules.is_empty() {
+            bail!("No win rules");
+        }
+
+        let mut patterns: Vec<Vec<(usize, usize)>> = Vec::new();
+        for rule in rules {
+            match rule {
+                WinRule::Rows => {
+                    patterns.extend((0..height).map(|y| (0..width).map(|x| (x, y)).collect()))
+                }
+                WinRule::Columns => {
+                    patterns.extend((0..width).map(|x| (0..height).map(|y| (x, y)).collect()))
+                }
+                WinRule::Diagonals => {
+                    if width != height {
+                        bail!("Diagonals need a square board, got {}x{}", width, height);
+                    }
+                    patterns.push((0..width).map(|i| (i, i)).collect());
+                    patterns.push((0..width).map(|i| (width - 1 - i, i)).collect());
+                }
+                WinRule::Corners => patterns.push(vec![
+                    (0, 0),
+                    (width - 1, 0),
+                    (0, height - 1),
+                    (width - 1, height - 1),
+                ]),
+                WinRule::Blackout => patterns.push(
+                    (0..height)
+                        .flat_map(|y| (0..width).map(move |x| (x, y)))
+                        .collect(),
+                ),
+                WinRule::Pattern(cells) => {
+                    if cells.is_empty() {
+                        bail!("Empty win pattern");
+                    }
+                    if let Some((x, y)) = cells.iter().find(|(x, y)| *x >= width || *y >= height) {
+                        bail!("Win pattern cell ({}, {}) is outside the board", x, y);
+                    }
+                    patterns.push(cells.clone());
+                }
+            }
+        }
+
+        let mut cells = Vec::with_capacity(patterns.len());
+        let mut by_cell = vec![Vec::new(); width * height];
+        for (i, pattern) in patterns.into_iter().enumerate() {
+            let mut pattern: Vec<usize> = pattern.into_iter().map(|(x, y)| y * width + x).collect();
+            // corners of a single row or column board, or a repeated cell in a custom pattern
+            pattern.sort_unstable();
+            pattern.dedup();
+
+            for &cell in &pattern {
+                by_cell[cell].push(i);
+            }
+            cells.push(pattern);
+        }
+
+        Ok(Self { cells, by_cell })
+    }
+}
+
+/// Hasher for the board lookup tables. The keys are small integers chosen by the puzzle input
+/// rather than an attacker, so a single multiply spreads them well enough and is much cheaper than
+/// the default SipHash on the hot path of marking every board on every draw.
+#[derive(Default)]
+struct NumberHasher(u64);
+
+impl Hasher for NumberHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0 ^ n).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    width: usize,
+    height: usize,
+    /// Cells in row-major order.
+    inner: Vec<(u32, Status)>,
+    /// The first pattern to be completed, if any.
+    winning_pattern: Option<usize>,
+    /// Position of each number on the board, so a draw can be marked without scanning every cell.
+    /// Never changes after the board is built, so copies of the board share it.
+    cells: Arc<HashMap<u32, usize, BuildHasherDefault<NumberHasher>>>,
+    patterns: Arc<Patterns>,
+    /// Uncalled cells left in each pattern, the board wins when any count hits 0.
+    remaining: Vec<usize>,
+    uncalled_sum: u64,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -15,121 +156,296 @@ pub enum Status {
     Uncalled,
 }
 
-impl<const WIDTH: usize, const HEIGHT: usize> Board<WIDTH, HEIGHT> {
-    pub fn play_turn(&mut self, draw: u8) -> Option<u32> {
-        if self.has_won {
+impl Board {
+    /// Builds a `width` x `height` board from `values` in row-major order, checking that the size
+    /// matches and no number appears twice.
+    pub fn new(width: usize, height: usize, values: &[u32]) -> anyhow::Result<Self> {
+        if width == 0 || height == 0 {
+            bail!("Empty board");
+        }
+        if values.len() != width * height {
+            bail!(
+                "Invalid board size, {} numbers for a {}x{} board",
+                values.len(),
+                width,
+                height
+            );
+        }
+
+        let mut cells = HashMap::with_capacity_and_hasher(values.len(), Default::default());
+        for (i, &value) in values.iter().enumerate() {
+            if cells.insert(value, i).is_some() {
+                bail!(
+                    "Duplicate number {} on board at row {}, column {}",
+                    value,
+                    i / width + 1,
+                    i % width + 1
+                );
+            }
+        }
+
+        let patterns = Patterns::new(&WinRule::LINES, width, height)?;
+        let mut board = Self {
+            width,
+            height,
+            inner: values
+                .iter()
+                .map(|&value| (value, Status::Uncalled))
+                .collect(),
+            winning_pattern: None,
+            cells: Arc::new(cells),
+            patterns: Arc::new(patterns),
+            remaining: Vec::new(),
+            uncalled_sum: 0,
+        };
+        board.recount();
+
+        Ok(board)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Replaces the rows and columns rule with the given rules, keeping any marks already made.
+    pub fn set_rules(&mut self, rules: &[WinRule]) -> anyhow::Result<()> {
+        self.patterns = Arc::new(Patterns::new(rules, self.width, self.height)?);
+        self.recount();
+
+        Ok(())
+    }
+
+    /// Recomputes the pattern counters and score from the marks on the board.
+    fn recount(&mut self) {
+        self.remaining = vec![0; self.patterns.cells.len()];
+        self.uncalled_sum = 0;
+
+        for (i, &(value, status)) in self.inner.iter().enumerate() {
+            if status == Status::Uncalled {
+                self.uncalled_sum += value as u64;
+                for &pattern in &self.patterns.by_cell[i] {
+                    self.remaining[pattern] += 1;
+                }
+            }
+        }
+
+        self.winning_pattern = self.remaining.iter().position(|&remaining| remaining == 0);
+    }
+
+    pub fn play_turn(&mut self, draw: u32) -> Option<u64> {
+        if self.has_won() {
             return None;
         }
 
         self.mark(draw);
 
         if self.has_won() {
-            self.has_won = true;
-            return Some(self.internal_score() * (draw as u32));
+            return Some(self.internal_score() * (draw as u64));
         }
 
         None
     }
-    pub fn mark(&mut self, draw: u8) {
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                if self.inner[y][x].0 == draw {
-                    self.inner[y][x].1 = Status::Called;
-                }
+
+    pub fn mark(&mut self, draw: u32) {
+        self.mark_cell(draw);
+    }
+
+    /// Marks `draw` and returns the index of the cell it was in, or None if it isn't on the board
+    /// or was already called.
+    fn mark_cell(&mut self, draw: u32) -> Option<usize> {
+        let i = *self.cells.get(&draw)?;
+
+        let cell = &mut self.inner[i];
+        if cell.1 == Status::Called {
+            return None;
+        }
+
+        cell.1 = Status::Called;
+        self.uncalled_sum -= cell.0 as u64;
+
+        for &pattern in &self.patterns.by_cell[i] {
+            self.remaining[pattern] -= 1;
+            if self.remaining[pattern] == 0 && self.winning_pattern.is_none() {
+                self.winning_pattern = Some(pattern);
             }
         }
+
+        Some(i)
+    }
+
+    /// Clears every mark on the board.
+    pub fn reset(&mut self) {
+        self.inner
+            .iter_mut()
+            .for_each(|cell| cell.1 = Status::Uncalled);
+        self.recount();
     }
 
     pub fn has_won(&self) -> bool {
-        if self.has_won {
-            return true;
-        }
+        self.winning_pattern.is_some()
+    }
+
+    /// The `(x, y)` cells of the first pattern the board completed.
+    pub fn winning_cells(&self) -> Option<Vec<(usize, usize)>> {
+        let pattern = &self.patterns.cells[self.winning_pattern?];
+
+        Some(pattern.iter().map(|&i| self.position(i)).collect())
+    }
 
-        let mut complete_columns = [true; WIDTH];
+    fn position(&self, i: usize) -> (usize, usize) {
+        (i % self.width, i / self.width)
+    }
+
+    /// Draws the board as a grid with called numbers and the winning cells highlighted.
+    pub fn render(&self, highlight: Highlight) -> String {
+        let digits = self
+            .inner
+            .iter()
+            .map(|(value, _)| value.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let winning = self
+            .winning_pattern
+            .map_or(&[][..], |pattern| &self.patterns.cells[pattern][..]);
 
-        for y in 0..HEIGHT {
-            let mut complete_row = true;
-            for x in 0..WIDTH {
-                let status = self.inner[y][x].1;
-                if status == Status::Uncalled {
-                    complete_row = false;
-                    complete_columns[x] = false;
+        let mut rendered = String::new();
+        for (y, row) in self.inner.chunks(self.width).enumerate() {
+            for (x, &(value, status)) in row.iter().enumerate() {
+                if x > 0 {
+                    rendered.push(' ');
                 }
+                let style = if winning.contains(&(y * self.width + x)) {
+                    CellStyle::Winning
+                } else if status == Status::Called {
+                    CellStyle::Called
+                } else {
+                    CellStyle::Uncalled
+                };
+                rendered.push_str(&highlight.cell(value, digits, style));
             }
-            if complete_row {
-                return true;
-            }
+            rendered.push('\n');
         }
 
-        complete_columns.into_iter().any(|complete| complete)
+        rendered
     }
 
-    pub fn internal_score(&self) -> u32 {
-        self.inner
-            .iter()
-            .flatten()
-            .copied()
-            .filter(|(_, status)| *status == Status::Uncalled)
-            .fold(0, |score, (value, _)| score + (value as u32))
+    pub fn internal_score(&self) -> u64 {
+        self.uncalled_sum
     }
 }
 
-impl<const WIDTH: usize, const HEIGHT: usize> From<&[u8]> for Board<WIDTH, HEIGHT> {
-    fn from(slice: &[u8]) -> Self {
-        if slice.len() != WIDTH * HEIGHT {
-            panic!("Invalid board size");
-        }
+/// How `Board::render` picks out called numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    /// Called numbers in `[brackets]` and winning cells in `<angle brackets>`, for plain text.
+    Brackets,
+    /// Called numbers in bold and winning cells in bold green, for terminals.
+    Ansi,
+}
 
-        let mut board = [[(0, Status::Uncalled); WIDTH]; HEIGHT];
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CellStyle {
+    Uncalled,
+    Called,
+    Winning,
+}
 
-        for (i, value) in slice.iter().copied().enumerate() {
-            board[i / HEIGHT][i % WIDTH].0 = value;
+impl Highlight {
+    fn cell(&self, value: u32, digits: usize, style: CellStyle) -> String {
+        match (self, style) {
+            (Highlight::Brackets, CellStyle::Uncalled) => format!(" {:>1$} ", value, digits),
+            (Highlight::Brackets, CellStyle::Called) => format!("[{:>1$}]", value, digits),
+            (Highlight::Brackets, CellStyle::Winning) => format!("<{:>1$}>", value, digits),
+            (Highlight::Ansi, CellStyle::Uncalled) => format!("{:>1$}", value, digits),
+            (Highlight::Ansi, CellStyle::Called) => format!("\x1b[1m{:>1$}\x1b[0m", value, digits),
+            (Highlight::Ansi, CellStyle::Winning) => {
+                format!("\x1b[1;32m{:>1$}\x1b[0m", value, digits)
+            }
         }
+    }
+}
 
-        Self {
-            inner: board,
-            has_won: false,
-        }
+impl Display for Board {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render(Highlight::Brackets))
     }
 }
 
-impl<const WIDTH: usize, const HEIGHT: usize> FromStr for Board<WIDTH, HEIGHT> {
+impl FromStr for Board {
     type Err = anyhow::Error;
 
+    /// Takes the width from the first row and checks every other row matches it.
     fn from_str(s: &str) -> anyhow::Result<Self> {
-        let mut board = [[(0, Status::Uncalled); WIDTH]; HEIGHT];
+        let mut values = Vec::new();
+        let mut width = 0;
+        let mut height = 0;
+
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            height += 1;
+            let row = line
+                .split_whitespace()
+                .map(|token| {
+                    token
+                        .parse::<u32>()
+                        .map_err(|e| anyhow!("row {}: invalid number {:?}: {}", height, token, e))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
 
-        for (y, line) in s.lines().enumerate() {
-            for (x, token) in line.split_whitespace().enumerate() {
-                let value: u8 = token.parse()?;
-                board[y][x].0 = value;
+            if height == 1 {
+                width = row.len();
+            } else if row.len() != width {
+                bail!(
+                    "row {}: expected {} numbers, found {}",
+                    height,
+                    width,
+                    row.len()
+                );
             }
+            values.extend(row);
         }
 
-        Ok(Self {
-            inner: board,
-            has_won: false,
-        })
+        Self::new(width, height, &values)
     }
 }
 
-impl<const WIDTH: usize, const HEIGHT: usize> Index<usize> for Board<WIDTH, HEIGHT> {
-    type Output = [(u8, Status); WIDTH];
+impl Index<usize> for Board {
+    type Output = [(u32, Status)];
 
+    /// Returns row `index`.
     fn index(&self, index: usize) -> &Self::Output {
-        &self.inner[index]
+        &self.inner[index * self.width..(index + 1) * self.width]
     }
 }
 
 #[derive(Clone, Debug)]
-pub struct Bingo<const BOARD_WIDTH: usize, const BOARD_HEIGHT: usize> {
-    pub sequence: Vec<u8>,
-    pub boards: Vec<Board<BOARD_WIDTH, BOARD_HEIGHT>>,
+pub struct Bingo {
+    pub sequence: Vec<u32>,
+    pub boards: Vec<Board>,
     pub turn: usize,
 }
 
-impl<const BOARD_WIDTH: usize, const BOARD_HEIGHT: usize> Bingo<BOARD_WIDTH, BOARD_HEIGHT> {
-    fn play_turn(&mut self) -> Option<u32> {
+impl Bingo {
+    /// Sets the rules every board is won by, replacing the default of rows and columns.
+    pub fn with_rules(mut self, rules: &[WinRule]) -> anyhow::Result<Self> {
+        let (width, height) = match self.boards.first() {
+            Some(board) => (board.width, board.height),
+            None => return Ok(self),
+        };
+
+        let patterns = Arc::new(Patterns::new(rules, width, height)?);
+        for board in self.boards.iter_mut() {
+            board.patterns = patterns.clone();
+            board.recount();
+        }
+
+        Ok(self)
+    }
+
+    fn play_turn(&mut self) -> Option<u64> {
         let draw = self.sequence[self.turn];
         self.turn += 1;
 
@@ -140,10 +456,10 @@ impl<const BOARD_WIDTH: usize, const BOARD_HEIGHT: usize> Bingo<BOARD_WIDTH, BOA
         self.boards
             .iter()
             .find(|board| board.has_won())
-            .map(|board| board.internal_score() * (draw as u32))
+            .map(|board| board.internal_score() * (draw as u64))
     }
 
-    pub fn play_game(&mut self) -> u32 {
+    pub fn play_game(&mut self) -> u64 {
         loop {
             if let Some(score) = self.play_turn() {
                 return score;
@@ -151,7 +467,7 @@ impl<const BOARD_WIDTH: usize, const BOARD_HEIGHT: usize> Bingo<BOARD_WIDTH, BOA
         }
     }
 
-    pub fn play_until_last(&mut self) -> u32 {
+    pub fn play_until_last(&mut self) -> u64 {
         let mut last_score = 0;
 
         for &draw in self.sequence.iter() {
@@ -164,34 +480,621 @@ impl<const BOARD_WIDTH: usize, const BOARD_HEIGHT: usize> Bingo<BOARD_WIDTH, BOA
 
         last_score
     }
+
+    /// Plays the whole call sequence on unmarked copies of the boards and returns every board in the
+    /// order it won. Boards that win on the same draw are ordered by index.
+    pub fn ranking(&self) -> Ranking {
+        let mut boards = self.boards.clone();
+        boards.iter_mut().for_each(Board::reset);
+
+        rank(&mut boards, &self.sequence)
+    }
+
+    /// Plays the whole call sequence on unmarked copies of the boards, recording every draw, every
+    /// cell marked and every win. Boards stop being marked once they've won.
+    pub fn events(&self) -> Vec<Event> {
+        let mut boards = self.boards.clone();
+        boards.iter_mut().for_each(Board::reset);
+
+        let mut events = Vec::new();
+        for (turn, &number) in self.sequence.iter().enumerate() {
+            events.push(Event::Draw {
+                turn: turn + 1,
+                number,
+            });
+
+            for (i, board) in boards.iter_mut().enumerate() {
+                if board.has_won() {
+                    continue;
+                }
+
+                if let Some(cell) = board.mark_cell(number) {
+                    let (x, y) = board.position(cell);
+                    events.push(Event::Marked { board: i, x, y });
+
+                    if let Some(cells) = board.winning_cells() {
+                        events.push(Event::Won {
+                            board: i,
+                            score: board.internal_score() * number as u64,
+                            cells,
+                        });
+                    }
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Steps through the game's events on unmarked copies of the boards.
+    pub fn replay(&self) -> Replay {
+        let mut boards = self.boards.clone();
+        boards.iter_mut().for_each(Board::reset);
+
+        Replay {
+            boards,
+            events: self.events(),
+            next: 0,
+            draw: None,
+        }
+    }
+
+    /// Builds the shortest call sequence, using each of the game's numbers at most once, that makes
+    /// `board` win in the place given by `goal`. Winning first means no other board wins on the
+    /// same draw, and winning last means every other board has won on an earlier draw.
+    ///
+    /// The search for `Goal::First` is always exact. For `Goal::Last` it gives up after exploring
+    /// `SEARCH_BUDGET` partial sequences, returning the best sequence found so far if there is one.
+    ///
+    /// Panics if `board` is out of range.
+    pub fn fastest_win(&self, board: usize, goal: Goal) -> SearchResult {
+        let mut pool: Vec<u32> = Vec::new();
+        let mut indices: HashMap<u32, usize> = HashMap::new();
+        for &number in self.sequence.iter() {
+            indices.entry(number).or_insert_with(|| {
+                pool.push(number);
+                pool.len() - 1
+            });
+        }
+
+        // winning patterns of each board as sets of numbers, leaving out any that can't be called
+        let patterns: Vec<Vec<NumberSet>> = self
+            .boards
+            .iter()
+            .map(|board| {
+                board
+                    .patterns
+                    .cells
+                    .iter()
+                    .filter_map(|cells| {
+                        let mut set = NumberSet::new(pool.len());
+                        for &cell in cells {
+                            set.insert(*indices.get(&board.inner[cell].0)?);
+                        }
+                        Some(set)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let target = &patterns[board];
+        let others: Vec<Vec<NumberSet>> = patterns
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != board)
+            .map(|(_, patterns)| patterns.clone())
+            .collect();
+        let to_sequence = |sets: &[&NumberSet]| {
+            let mut called = vec![false; pool.len()];
+            let mut sequence = Vec::new();
+            for set in sets {
+                for i in set.iter() {
+                    if !std::mem::replace(&mut called[i], true) {
+                        sequence.push(pool[i]);
+                    }
+                }
+            }
+            CallSequence(sequence)
+        };
+
+        match goal {
+            Goal::First => target
+                .iter()
+                .filter(|p| !others.iter().flatten().any(|q| q.is_subset(p)))
+                .min_by_key(|p| p.len())
+                .map_or(SearchResult::Impossible, |p| SearchResult::Found {
+                    sequence: to_sequence(&[p]),
+                    optimal: true,
+                }),
+            Goal::Last => {
+                if target.is_empty() || others.iter().any(Vec::is_empty) {
+                    return SearchResult::Impossible;
+                }
+
+                let mut search = LastSearch {
+                    target,
+                    others: &others,
+                    best: None,
+                    nodes: 0,
+                    exhausted: false,
+                };
+                let remaining: Vec<usize> = (0..others.len()).collect();
+                search.search(&NumberSet::new(pool.len()), &mut Vec::new(), &remaining);
+
+                match (search.best, search.exhausted) {
+                    (Some((_, sets)), exhausted) => SearchResult::Found {
+                        sequence: to_sequence(&sets),
+                        optimal: !exhausted,
+                    },
+                    (None, false) => SearchResult::Impossible,
+                    (None, true) => SearchResult::Exhausted,
+                }
+            }
+        }
+    }
+
+    /// Plays `trials` games with the call sequence shuffled at random and reports how often each
+    /// board wins, wins first or wins last, and how many draws it takes on average. Boards that
+    /// win on the same draw share the place, so the first and last probabilities can sum to more
+    /// than 1.
+    ///
+    /// Trials run in parallel, each with its own stream from an RNG seeded with `seed`, so the
+    /// results only depend on the seed and number of trials.
+    pub fn simulate(&self, trials: usize, seed: u64) -> Simulation {
+        // each worker reuses one copy of the boards and the sequence for all of its trials
+        let tally = (0..trials)
+            .into_par_iter()
+            .fold(
+                || {
+                    let tally = Tally::new(self.boards.len());
+                    (tally, self.boards.clone(), self.sequence.clone())
+                },
+                |(mut tally, mut boards, mut sequence), trial| {
+                    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                    rng.set_stream(trial as u64);
+
+                    boards.iter_mut().for_each(Board::reset);
+                    sequence.copy_from_slice(&self.sequence);
+                    sequence.shuffle(&mut rng);
+                    tally.add(&rank(&mut boards, &sequence));
+
+                    (tally, boards, sequence)
+                },
+            )
+            .map(|(tally, _, _)| tally)
+            .reduce(|| Tally::new(self.boards.len()), Tally::merge);
+
+        tally.into_simulation(trials)
+    }
+}
+
+/// Plays `sequence` on `boards`, which should be unmarked, and returns every board in the order it
+/// won, see `Bingo::ranking`.
+fn rank(boards: &mut [Board], sequence: &[u32]) -> Ranking {
+    let mut wins = Vec::new();
+    for (turn, &draw) in sequence.iter().enumerate() {
+        for (board, state) in boards.iter_mut().enumerate() {
+            if let Some(score) = state.play_turn(draw) {
+                wins.push(Win {
+                    board,
+                    draw,
+                    turn: turn + 1,
+                    score,
+                });
+            }
+        }
+    }
+
+    let never_won = boards
+        .iter()
+        .enumerate()
+        .filter(|(_, board)| !board.has_won())
+        .map(|(board, _)| board)
+        .collect();
+
+    Ranking { wins, never_won }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A number was called, `turn` counts from 1.
+    Draw { turn: usize, number: u32 },
+    /// The last number called was at `(x, y)` on `board`.
+    Marked { board: usize, x: usize, y: usize },
+    /// `board` won on the last number called by completing `cells`.
+    Won {
+        board: usize,
+        score: u64,
+        cells: Vec<(usize, usize)>,
+    },
 }
 
-impl<const BOARD_WIDTH: usize, const BOARD_HEIGHT: usize> FromStr
-    for Bingo<BOARD_WIDTH, BOARD_HEIGHT>
-{
+/// A game's event log along with the state of the boards after the events applied so far.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    boards: Vec<Board>,
+    events: Vec<Event>,
+    next: usize,
+    draw: Option<u32>,
+}
+
+impl Replay {
+    pub fn boards(&self) -> &[Board] {
+        &self.boards
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// The last number called.
+    pub fn draw(&self) -> Option<u32> {
+        self.draw
+    }
+
+    /// Applies the next event to the boards and returns it, or None once every event is applied.
+    pub fn step(&mut self) -> Option<&Event> {
+        let event = self.events.get(self.next)?;
+        self.next += 1;
+
+        match *event {
+            Event::Draw { number, .. } => self.draw = Some(number),
+            Event::Marked { board, x, y } => {
+                let board = &mut self.boards[board];
+                let number = board[y][x].0;
+                board.mark(number);
+            }
+            Event::Won { .. } => {}
+        }
+
+        Some(event)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Win {
+    /// Index of the board in `Bingo::boards`.
+    pub board: usize,
+    /// The number that completed the board.
+    pub draw: u32,
+    /// How many numbers had been called when the board won, starting at 1.
+    pub turn: usize,
+    pub score: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ranking {
+    /// Winning boards in the order they won.
+    pub wins: Vec<Win>,
+    /// Indices of the boards that are still incomplete after the last draw.
+    pub never_won: Vec<usize>,
+}
+
+impl Ranking {
+    pub fn first(&self) -> Option<&Win> {
+        self.wins.first()
+    }
+
+    pub fn last(&self) -> Option<&Win> {
+        self.wins.last()
+    }
+
+    pub fn position(&self, board: usize) -> Option<usize> {
+        self.wins.iter().position(|win| win.board == board)
+    }
+}
+
+/// Which place the target board should win in when searching for a call sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// Win before any other board.
+    First,
+    /// Win after every other board has won.
+    Last,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchResult {
+    /// A call sequence meeting the goal. `optimal` is false if the search ran out of budget before
+    /// it could rule out a shorter sequence.
+    Found {
+        sequence: CallSequence,
+        optimal: bool,
+    },
+    /// No order of the game's numbers meets the goal.
+    Impossible,
+    /// The search ran out of budget without finding a sequence or proving there isn't one.
+    Exhausted,
+}
+
+/// Numbers to call, displayed comma-separated as in the first line of the puzzle input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallSequence(pub Vec<u32>);
+
+impl Display for CallSequence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let numbers: Vec<String> = self.0.iter().map(u32::to_string).collect();
+        f.write_str(&numbers.join(","))
+    }
+}
+
+/// Maximum number of partial sequences `Bingo::fastest_win` explores when the goal is `Last`.
+const SEARCH_BUDGET: usize = 100_000;
+
+/// Set of indices into the pool of numbers that can be called.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct NumberSet(Vec<u64>);
+
+impl NumberSet {
+    fn new(len: usize) -> Self {
+        Self(vec![0; len.div_ceil(64)])
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self(self.0.iter().zip(&other.0).map(|(a, b)| a | b).collect())
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Number of elements of `self` not in `other`.
+    fn difference_len(&self, other: &Self) -> usize {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(a, b)| (a & !b).count_ones() as usize)
+            .sum()
+    }
+
+    fn is_subset(&self, other: &Self) -> bool {
+        self.difference_len(other) == 0
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.0.len() * 64).filter(move |&i| self.0[i / 64] & (1 << (i % 64)) != 0)
+    }
+}
+
+/// Branch and bound search for the shortest set of calls that wins every other board without
+/// winning the target, followed by the fewest extra calls to then win the target.
+struct LastSearch<'a> {
+    /// The target board's winning patterns.
+    target: &'a [NumberSet],
+    /// Every other board's winning patterns.
+    others: &'a [Vec<NumberSet>],
+    /// Length of the best sequence found along with the patterns that make it up, in call order.
+    best: Option<(usize, Vec<&'a NumberSet>)>,
+    nodes: usize,
+    exhausted: bool,
+}
+
+impl<'a> LastSearch<'a> {
+    fn search(&mut self, called: &NumberSet, chosen: &mut Vec<&'a NumberSet>, remaining: &[usize]) {
+        if self.nodes >= SEARCH_BUDGET {
+            self.exhausted = true;
+            return;
+        }
+        self.nodes += 1;
+
+        let best = self.best.as_ref().map_or(usize::MAX, |(len, _)| *len);
+        let remaining: Vec<usize> = remaining
+            .iter()
+            .copied()
+            .filter(|&j| !self.others[j].iter().any(|q| q.is_subset(called)))
+            .collect();
+
+        if remaining.is_empty() {
+            let finish = self
+                .target
+                .iter()
+                .min_by_key(|p| p.difference_len(called))
+                .expect("target has patterns");
+            let len = called.len() + finish.difference_len(called);
+            if len < best {
+                let mut patterns = chosen.clone();
+                patterns.push(finish);
+                self.best = Some((len, patterns));
+            }
+            return;
+        }
+
+        // patterns that would win each remaining board without also winning the target, cheapest
+        // first
+        let mut options: Vec<(usize, Vec<&'a NumberSet>)> = Vec::with_capacity(remaining.len());
+        for &j in remaining.iter() {
+            let mut patterns: Vec<&'a NumberSet> = self.others[j]
+                .iter()
+                .filter(|q| {
+                    let next = called.union(q);
+                    !self.target.iter().any(|p| p.is_subset(&next))
+                })
+                .collect();
+            if patterns.is_empty() {
+                return;
+            }
+            patterns.sort_by_key(|q| q.difference_len(called));
+            options.push((j, patterns));
+        }
+
+        // every remaining board needs at least its cheapest pattern and the target one more call
+        let lower_bound = options
+            .iter()
+            .map(|(_, patterns)| patterns[0].difference_len(called))
+            .max()
+            .unwrap_or(0);
+        if called.len() + lower_bound + 1 >= best {
+            return;
+        }
+
+        // branch on the board with the fewest ways to win
+        let (j, patterns) = options
+            .iter()
+            .min_by_key(|(_, patterns)| patterns.len())
+            .expect("remaining is not empty");
+        let remaining: Vec<usize> = remaining.iter().copied().filter(|i| i != j).collect();
+        for &q in patterns.iter() {
+            chosen.push(q);
+            self.search(&called.union(q), chosen, &remaining);
+            chosen.pop();
+        }
+    }
+}
+
+/// Running totals for each board across simulated games.
+#[derive(Clone, Debug)]
+struct Tally {
+    first: Vec<u64>,
+    last: Vec<u64>,
+    won: Vec<u64>,
+    turns: Vec<u64>,
+}
+
+impl Tally {
+    fn new(boards: usize) -> Self {
+        Self {
+            first: vec![0; boards],
+            last: vec![0; boards],
+            won: vec![0; boards],
+            turns: vec![0; boards],
+        }
+    }
+
+    fn add(&mut self, ranking: &Ranking) {
+        let (first, last) = match (ranking.first(), ranking.last()) {
+            (Some(first), Some(last)) => (first.turn, last.turn),
+            _ => return,
+        };
+
+        for win in ranking.wins.iter() {
+            self.won[win.board] += 1;
+            self.turns[win.board] += win.turn as u64;
+            if win.turn == first {
+                self.first[win.board] += 1;
+            }
+            if win.turn == last {
+                self.last[win.board] += 1;
+            }
+        }
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        for (totals, others) in [
+            (&mut self.first, other.first),
+            (&mut self.last, other.last),
+            (&mut self.won, other.won),
+            (&mut self.turns, other.turns),
+        ] {
+            totals
+                .iter_mut()
+                .zip(others)
+                .for_each(|(total, other)| *total += other);
+        }
+
+        self
+    }
+
+    fn into_simulation(self, trials: usize) -> Simulation {
+        let probability = |count: u64| {
+            if trials == 0 {
+                0.0
+            } else {
+                count as f64 / trials as f64
+            }
+        };
+
+        let boards = (0..self.won.len())
+            .map(|i| BoardStats {
+                first: probability(self.first[i]),
+                last: probability(self.last[i]),
+                wins: probability(self.won[i]),
+                mean_turn: (self.won[i] > 0).then(|| self.turns[i] as f64 / self.won[i] as f64),
+            })
+            .collect();
+
+        Simulation { trials, boards }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Simulation {
+    pub trials: usize,
+    /// Statistics for each board, in the same order as `Bingo::boards`.
+    pub boards: Vec<BoardStats>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoardStats {
+    /// Probability of winning on the first winning draw.
+    pub first: f64,
+    /// Probability of winning on the last winning draw.
+    pub last: f64,
+    /// Probability of winning at all before the numbers run out.
+    pub wins: f64,
+    /// Mean number of draws to win, over the games the board won.
+    pub mean_turn: Option<f64>,
+}
+
+impl FromStr for Bingo {
     type Err = anyhow::Error;
 
+    /// Checks that every board has the same size as the first.
     fn from_str(s: &str) -> anyhow::Result<Self> {
-        let mut spliterator = s.split("\n\n");
+        let mut spliterator = s.split("\n\n").filter(|chunk| !chunk.trim().is_empty());
 
-        let sequence: Vec<u8> = if let Some(sequence) = spliterator.next() {
+        let sequence: Vec<u32> = if let Some(sequence) = spliterator.next() {
             sequence
+                .trim()
                 .split(',')
-                .map(|token| token.parse())
-                .collect::<Result<_, _>>()?
+                .map(|token| {
+                    token
+                        .parse()
+                        .map_err(|e| anyhow!("call sequence: invalid number {:?}: {}", token, e))
+                })
+                .collect::<anyhow::Result<_>>()?
         } else {
             bail!("Missing call sequence!");
         };
 
-        let boards: Vec<Board<BOARD_WIDTH, BOARD_HEIGHT>> = spliterator
-            .map(|board| board.parse())
-            .collect::<Result<_, _>>()?;
+        let boards: Vec<Board> = spliterator
+            .enumerate()
+            .map(|(i, board)| {
+                board
+                    .parse()
+                    .map_err(|e: anyhow::Error| anyhow!("board {}: {}", i + 1, e))
+            })
+            .collect::<anyhow::Result<_>>()?;
 
-        Ok(Self {
+        let (width, height) = match boards.first() {
+            Some(board) => (board.width, board.height),
+            None => bail!("No boards"),
+        };
+        if let Some((i, board)) = boards
+            .iter()
+            .enumerate()
+            .find(|(_, board)| (board.width, board.height) != (width, height))
+        {
+            bail!(
+                "board {}: expected a {}x{} board, found {}x{}",
+                i + 1,
+                width,
+                height,
+                board.width,
+                board.height
+            );
+        }
+
+        // share a single copy of the rows and columns patterns between every board
+        Self {
             sequence,
             boards,
             turn: 0,
-        })
+        }
+        .with_rules(&WinRule::LINES)
     }
 }
 
@@ -199,39 +1102,101 @@ impl<const BOARD_WIDTH: usize, const BOARD_HEIGHT: usize> FromStr
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "\
+7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+
+22 13 17 11  0
+ 8  2 23  4 24
+21  9 14 16  7
+ 6 10  3 18  5
+ 1 12 20 15 19
+
+ 3 15  0  2 22
+ 9 18 13 17  5
+19  8  7 25 23
+20 11 10 24  4
+14 21 16 12  6
+
+14 21 17 24  4
+10 16 15  9 19
+18  8 23 26 20
+22 11 13  6  5
+ 2  0 12  3  7";
+
     #[test]
     fn parse_from_str() {
         let board = "23 45\n 47 90";
-        let board: Board<2, 2> = board.parse().unwrap();
-        let expected: Board<2, 2> = Board {
-            inner: [
-                [(23, Status::Uncalled), (45, Status::Uncalled)],
-                [(47, Status::Uncalled), (90, Status::Uncalled)],
-            ],
-            has_won: false,
-        };
+        let board: Board = board.parse().unwrap();
+        let expected = Board::new(2, 2, &[23, 45, 47, 90]).unwrap();
         assert_eq!(board, expected);
     }
 
     #[test]
-    fn from_slice() {
-        let board: [u8; 4] = [23, 45, 47, 90];
-        let board: Board<2, 2> = board[..].into();
-        let expected: Board<2, 2> = Board {
-            inner: [
-                [(23, Status::Uncalled), (45, Status::Uncalled)],
-                [(47, Status::Uncalled), (90, Status::Uncalled)],
-            ],
-            has_won: false,
-        };
+    fn new_non_square() {
+        let board = Board::new(3, 2, &[1, 2, 3, 4, 5, 6]).unwrap();
 
-        assert_eq!(board, expected);
+        assert_eq!(board.width(), 3);
+        assert_eq!(board.height(), 2);
+        assert_eq!(
+            board[0].iter().map(|cell| cell.0).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            board[1].iter().map(|cell| cell.0).collect::<Vec<_>>(),
+            [4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn parse_non_square_and_wide_numbers() {
+        let board: Board = "1000 2 3\n4 5 70000".parse().unwrap();
+
+        assert_eq!(board.width(), 3);
+        assert_eq!(board.height(), 2);
+        assert_eq!(board[1][2].0, 70000);
+    }
+
+    #[test]
+    fn errors_on_invalid_boards() {
+        assert!("1 2 3\n4 5".parse::<Board>().is_err());
+        assert!("1 2\n3 4 5".parse::<Board>().is_err());
+        assert!("1 -2\n3 4".parse::<Board>().is_err());
+        assert!("".parse::<Board>().is_err());
+        assert!(Board::new(2, 2, &[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn errors_on_mismatched_board_sizes() {
+        let error = "1,2\n\n1 2\n3 4\n\n1 2 3\n4 5 6"
+            .parse::<Bingo>()
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "board 2: expected a 2x2 board, found 3x2"
+        );
+    }
+
+    #[test]
+    fn errors_are_positioned() {
+        let error = "1,2\n\n1 2\n3 4\n\n1 2\n3 x".parse::<Bingo>().unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("board 2: row 2: invalid number \"x\""));
+
+        let error = "1,2\n\n1 2\n2 4".parse::<Bingo>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "board 1: Duplicate number 2 on board at row 2, column 1"
+        );
+
+        assert!("".parse::<Bingo>().is_err());
+        assert!("1,2".parse::<Bingo>().is_err());
     }
 
     #[test]
     fn index_works() {
-        let board: [u8; 4] = [23, 45, 47, 90];
-        let board: Board<2, 2> = board[..].into();
+        let board = Board::new(2, 2, &[23, 45, 47, 90]).unwrap();
 
         assert_eq!(board[0][0].0, 23);
         assert_eq!(board[0][1].0, 45);
@@ -241,8 +1206,7 @@ mod tests {
 
     #[test]
     fn draw_value() {
-        let board: [u8; 4] = [23, 45, 47, 90];
-        let mut board: Board<2, 2> = board[..].into();
+        let mut board = Board::new(2, 2, &[23, 45, 47, 90]).unwrap();
         board.mark(45);
 
         assert_eq!(board[0][1].1, Status::Called);
@@ -250,8 +1214,7 @@ mod tests {
 
     #[test]
     fn has_won_row() {
-        let board: [u8; 4] = [23, 45, 47, 90];
-        let mut board: Board<2, 2> = board[..].into();
+        let mut board = Board::new(2, 2, &[23, 45, 47, 90]).unwrap();
 
         assert!(!board.has_won());
 
@@ -264,8 +1227,7 @@ mod tests {
 
     #[test]
     fn has_won_column() {
-        let board: [u8; 4] = [23, 45, 47, 90];
-        let mut board: Board<2, 2> = board[..].into();
+        let mut board = Board::new(2, 2, &[23, 45, 47, 90]).unwrap();
 
         assert!(!board.has_won());
 
@@ -278,12 +1240,382 @@ mod tests {
 
     #[test]
     fn score() {
-        let board: [u8; 4] = [23, 45, 47, 90];
-        let mut board: Board<2, 2> = board[..].into();
+        let mut board = Board::new(2, 2, &[23, 45, 47, 90]).unwrap();
 
         board.mark(45);
 
         board.mark(90);
         assert_eq!(board.internal_score(), 70);
     }
+
+    #[test]
+    fn marking_twice_does_not_change_score() {
+        let mut board = Board::new(2, 2, &[23, 45, 47, 90]).unwrap();
+
+        board.mark(45);
+        board.mark(45);
+        assert_eq!(board.internal_score(), 160);
+        assert!(!board.has_won());
+    }
+
+    #[test]
+    fn ranking() {
+        let bingo: Bingo = EXAMPLE.parse().unwrap();
+        let ranking = bingo.ranking();
+
+        assert_eq!(
+            ranking.wins,
+            vec![
+                Win {
+                    board: 2,
+                    draw: 24,
+                    turn: 12,
+                    score: 4512
+                },
+                Win {
+                    board: 0,
+                    draw: 16,
+                    turn: 14,
+                    score: 2192
+                },
+                Win {
+                    board: 1,
+                    draw: 13,
+                    turn: 15,
+                    score: 1924
+                },
+            ]
+        );
+        assert!(ranking.never_won.is_empty());
+        assert_eq!(ranking.first().unwrap().score, 4512);
+        assert_eq!(ranking.last().unwrap().score, 1924);
+        assert_eq!(ranking.position(0), Some(1));
+    }
+
+    #[test]
+    fn ranking_ignores_marks_and_reports_boards_that_never_win() {
+        let mut bingo: Bingo = "1,2,3\n\n1 2\n3 4\n\n5 6\n7 8".parse().unwrap();
+        bingo.play_game();
+        let ranking = bingo.ranking();
+
+        assert_eq!(ranking.wins.len(), 1);
+        assert_eq!(ranking.wins[0].turn, 2);
+        assert_eq!(ranking.wins[0].score, 14);
+        assert_eq!(ranking.never_won, vec![1]);
+    }
+
+    #[test]
+    fn has_won_diagonal() {
+        let mut board: Board = "1 2 3\n4 5 6\n7 8 9".parse().unwrap();
+        board.set_rules(&[WinRule::Diagonals]).unwrap();
+
+        board.mark(1);
+        board.mark(2);
+        board.mark(3);
+        assert!(!board.has_won());
+
+        board.mark(5);
+        board.mark(7);
+        assert!(board.has_won());
+    }
+
+    #[test]
+    fn has_won_corners() {
+        let mut board: Board = "1 2 3\n4 5 6\n7 8 9".parse().unwrap();
+        board.set_rules(&[WinRule::Corners]).unwrap();
+
+        board.mark(1);
+        board.mark(3);
+        board.mark(7);
+        assert!(!board.has_won());
+
+        board.mark(9);
+        assert!(board.has_won());
+        assert_eq!(board.internal_score(), 25);
+    }
+
+    #[test]
+    fn has_won_blackout() {
+        let mut board: Board = "1 2\n3 4".parse().unwrap();
+        board.set_rules(&[WinRule::Blackout]).unwrap();
+
+        assert_eq!(board.play_turn(1), None);
+        assert_eq!(board.play_turn(2), None);
+        assert_eq!(board.play_turn(3), None);
+        assert_eq!(board.play_turn(4), Some(0));
+    }
+
+    #[test]
+    fn has_won_pattern() {
+        let mut board: Board = "1 2 3\n4 5 6\n7 8 9".parse().unwrap();
+        board
+            .set_rules(&[WinRule::Pattern(vec![(1, 0), (0, 1), (2, 1), (1, 2)])])
+            .unwrap();
+
+        for draw in [2, 4, 6] {
+            board.mark(draw);
+        }
+        assert!(!board.has_won());
+
+        board.mark(8);
+        assert!(board.has_won());
+    }
+
+    #[test]
+    fn set_rules_keeps_marks() {
+        let mut board: Board = "1 2\n3 4".parse().unwrap();
+        board.set_rules(&[WinRule::Blackout]).unwrap();
+        board.mark(1);
+        board.mark(2);
+        assert!(!board.has_won());
+
+        board.set_rules(&WinRule::LINES).unwrap();
+        assert!(board.has_won());
+    }
+
+    #[test]
+    fn invalid_rules() {
+        let board: Board = "1 2\n3 4\n5 6".parse().unwrap();
+
+        assert!(board.clone().set_rules(&[]).is_err());
+        assert!(board.clone().set_rules(&[WinRule::Diagonals]).is_err());
+        assert!(board
+            .clone()
+            .set_rules(&[WinRule::Pattern(vec![(2, 0)])])
+            .is_err());
+    }
+
+    #[test]
+    fn play_with_rules() {
+        let bingo: Bingo = EXAMPLE.parse().unwrap();
+        let ranking = bingo
+            .clone()
+            .with_rules(&[WinRule::Rows, WinRule::Columns, WinRule::Diagonals])
+            .unwrap()
+            .ranking();
+
+        // board 2 completes the diagonal 4, 9, 23, 11, 2 on the 8th draw
+        assert_eq!(ranking.wins[0].board, 2);
+        assert_eq!(ranking.wins[0].turn, 8);
+        assert_eq!(ranking.wins[0].draw, 2);
+
+        let ranking = bingo.with_rules(&[WinRule::Diagonals]).unwrap().ranking();
+        let order: Vec<(usize, usize)> = ranking
+            .wins
+            .iter()
+            .map(|win| (win.board, win.turn))
+            .collect();
+        assert_eq!(order, vec![(2, 8), (1, 20), (0, 24)]);
+    }
+
+    #[test]
+    fn events() {
+        let bingo: Bingo = "3,1,2\n\n1 2\n3 4\n\n5 6\n7 8".parse().unwrap();
+
+        assert_eq!(
+            bingo.events(),
+            vec![
+                Event::Draw { turn: 1, number: 3 },
+                Event::Marked {
+                    board: 0,
+                    x: 0,
+                    y: 1
+                },
+                Event::Draw { turn: 2, number: 1 },
+                Event::Marked {
+                    board: 0,
+                    x: 0,
+                    y: 0
+                },
+                Event::Won {
+                    board: 0,
+                    score: 6,
+                    cells: vec![(0, 0), (0, 1)]
+                },
+                Event::Draw { turn: 3, number: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_matches_ranking() {
+        let bingo: Bingo = EXAMPLE.parse().unwrap();
+        let mut replay = bingo.replay();
+
+        let mut wins = Vec::new();
+        while let Some(event) = replay.step() {
+            if let Event::Won { board, score, .. } = *event {
+                wins.push((board, score));
+            }
+        }
+
+        assert_eq!(wins, vec![(2, 4512), (0, 2192), (1, 1924)]);
+        assert_eq!(replay.draw(), Some(1));
+        assert!(replay.boards().iter().all(Board::has_won));
+        assert_eq!(replay.boards()[1].internal_score(), 148);
+    }
+
+    #[test]
+    fn render() {
+        let mut board: Board = "1 2\n3 40".parse().unwrap();
+        board.mark(2);
+        assert_eq!(board.to_string(), "  1  [ 2]\n  3   40 \n");
+
+        board.mark(40);
+        assert_eq!(board.to_string(), "  1  < 2>\n  3  <40>\n");
+        assert_eq!(board.winning_cells(), Some(vec![(1, 0), (1, 1)]));
+
+        assert_eq!(
+            board.render(Highlight::Ansi),
+            " 1 \x1b[1;32m 2\x1b[0m\n 3 \x1b[1;32m40\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn simulate() {
+        let bingo: Bingo = EXAMPLE.parse().unwrap();
+        let simulation = bingo.simulate(2000, 42);
+
+        assert_eq!(simulation.trials, 2000);
+        assert_eq!(simulation, bingo.simulate(2000, 42));
+        for stats in simulation.boards.iter() {
+            // every number on the boards is called, so every board wins eventually
+            assert_eq!(stats.wins, 1.0);
+            assert!(stats.first > 0.0 && stats.first < 1.0);
+            assert!(stats.last > 0.0 && stats.last < 1.0);
+            let mean_turn = stats.mean_turn.unwrap();
+            assert!((5.0..=27.0).contains(&mean_turn));
+        }
+        let first: f64 = simulation.boards.iter().map(|stats| stats.first).sum();
+        assert!(first >= 1.0);
+    }
+
+    #[test]
+    fn simulate_board_that_cannot_win() {
+        let bingo: Bingo = "1,2,3,4\n\n1 2\n3 4\n\n5 6\n7 8".parse().unwrap();
+        let simulation = bingo.simulate(100, 7);
+
+        assert_eq!(simulation.boards[0].first, 1.0);
+        assert_eq!(simulation.boards[0].last, 1.0);
+        assert_eq!(
+            simulation.boards[1],
+            BoardStats {
+                first: 0.0,
+                last: 0.0,
+                wins: 0.0,
+                mean_turn: None
+            }
+        );
+    }
+
+    /// Plays `sequence` on the example boards after checking it round trips through the input
+    /// format.
+    fn play_sequence(bingo: &Bingo, sequence: &CallSequence) -> Ranking {
+        let input = format!("{}\n\n{}", sequence, EXAMPLE.split_once("\n\n").unwrap().1);
+        let game: Bingo = input.parse().unwrap();
+        assert_eq!(game.sequence, sequence.0);
+        assert_eq!(game.boards, bingo.boards);
+
+        game.ranking()
+    }
+
+    #[test]
+    fn fastest_win_first() {
+        let bingo: Bingo = EXAMPLE.parse().unwrap();
+
+        for board in 0..3 {
+            let (sequence, optimal) = match bingo.fastest_win(board, Goal::First) {
+                SearchResult::Found { sequence, optimal } => (sequence, optimal),
+                result => panic!("no sequence for board {}: {:?}", board, result),
+            };
+            let ranking = play_sequence(&bingo, &sequence);
+
+            assert!(optimal);
+            assert_eq!(sequence.0.len(), 5);
+            assert_eq!(ranking.wins.len(), 1);
+            assert_eq!(ranking.wins[0].board, board);
+            assert_eq!(ranking.wins[0].turn, 5);
+        }
+    }
+
+    #[test]
+    fn fastest_win_last() {
+        let bingo: Bingo = EXAMPLE.parse().unwrap();
+
+        for board in 0..3 {
+            let (sequence, optimal) = match bingo.fastest_win(board, Goal::Last) {
+                SearchResult::Found { sequence, optimal } => (sequence, optimal),
+                result => panic!("no sequence for board {}: {:?}", board, result),
+            };
+            let ranking = play_sequence(&bingo, &sequence);
+
+            assert!(optimal);
+            assert_eq!(ranking.wins.len(), 3);
+            assert_eq!(ranking.last().unwrap().board, board);
+            assert_eq!(ranking.last().unwrap().turn, sequence.0.len());
+            assert!(ranking.wins[1].turn < sequence.0.len());
+        }
+    }
+
+    #[test]
+    fn fastest_win_last_is_shortest() {
+        // boards 1 and 2 both win with their first rows after calling 1, 2, 7 and 11, then board 0
+        // only needs 3 to complete its first row
+        let bingo: Bingo = "14,13,12,11,10,9,8,7,6,5,4,3,2,1\n\n\
+            1 2 3\n4 5 6\n\n\
+            1 2 7\n8 9 10\n\n\
+            1 2 11\n12 13 14"
+            .parse::<Bingo>()
+            .unwrap()
+            .with_rules(&[WinRule::Rows])
+            .unwrap();
+
+        match bingo.fastest_win(0, Goal::Last) {
+            SearchResult::Found { sequence, optimal } => {
+                assert!(optimal);
+                assert_eq!(sequence.0.len(), 5);
+                assert_eq!(sequence.0.last(), Some(&3));
+            }
+            result => panic!("no sequence: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn fastest_win_impossible() {
+        // identical boards always win together
+        let bingo: Bingo = "1,2,3,4\n\n1 2\n3 4\n\n1 2\n3 4".parse().unwrap();
+        assert_eq!(bingo.fastest_win(0, Goal::First), SearchResult::Impossible);
+        assert_eq!(bingo.fastest_win(0, Goal::Last), SearchResult::Impossible);
+
+        // board 1 can't win as 7 and 8 are never called
+        let bingo: Bingo = "1,2,3,4,5,6\n\n1 2\n3 4\n\n5 6\n7 8"
+            .parse::<Bingo>()
+            .unwrap()
+            .with_rules(&[WinRule::Blackout])
+            .unwrap();
+        assert_eq!(bingo.fastest_win(0, Goal::Last), SearchResult::Impossible);
+        assert_eq!(bingo.fastest_win(1, Goal::First), SearchResult::Impossible);
+        assert!(matches!(
+            bingo.fastest_win(0, Goal::First),
+            SearchResult::Found { .. }
+        ));
+    }
+
+    #[test]
+    fn call_sequence_display() {
+        assert_eq!(CallSequence(vec![7, 4, 19]).to_string(), "7,4,19");
+    }
+
+    #[test]
+    #[should_panic]
+    fn errors_on_duplicate_number() {
+        let _board: Board = "23 45\n45 90".parse().unwrap();
+    }
+
+    #[test]
+    fn play_example() {
+        let mut bingo: Bingo = EXAMPLE.parse().unwrap();
+        assert_eq!(bingo.clone().play_game(), 4512);
+        assert_eq!(bingo.play_until_last(), 1924);
+    }
 }
@@ -1,56 +1,93 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 use anyhow::bail;
-use nom::character::complete::multispace0;
-use nom::sequence::preceded;
+use nom::character::complete::{multispace0, space0};
+use nom::sequence::{delimited, preceded, terminated};
 use nom::{
-    bytes::complete::tag,
-    character::complete::char,
-    character::complete::digit1,
-    combinator::{map, map_res},
-    sequence::separated_pair,
-    Finish, IResult, Parser,
+    bytes::complete::tag, character::complete::char, sequence::separated_pair, Finish, IResult,
+    Parser,
 };
 
+/// Largest absolute coordinate of any point, small enough that nothing done with points or lines
+/// made from them can overflow.
+pub const MAX_COORDINATE: i64 = 1 << 40;
+
+/// Most cells `Lines::overlap_grid` will allocate, as the bounding box of a few lines can be far
+/// too big to hold in memory.
+pub const MAX_GRID_CELLS: u64 = 1 << 26;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Point {
-    x: i32,
-    y: i32,
+    x: i64,
+    y: i64,
 }
 
 impl Point {
-    fn new(x: i32, y: i32) -> Self {
-        Self { x, y }
+    /// Panics if either coordinate is beyond ±`MAX_COORDINATE`, `Point::try_from` checks them
+    /// instead.
+    pub fn new(x: i64, y: i64) -> Self {
+        Self::try_from((x, y)).unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
-impl From<(i32, i32)> for Point {
-    fn from(point: (i32, i32)) -> Self {
-        Self {
-            x: point.0,
-            y: point.1,
+impl TryFrom<(i64, i64)> for Point {
+    type Error = anyhow::Error;
+
+    fn try_from((x, y): (i64, i64)) -> anyhow::Result<Self> {
+        for coordinate in [x, y] {
+            if coordinate.unsigned_abs() > MAX_COORDINATE as u64 {
+                bail!(
+                    "coordinate {} is out of range, it must be within ±{}",
+                    coordinate,
+                    MAX_COORDINATE
+                );
+            }
         }
+
+        Ok(Self { x, y })
     }
 }
 
-impl std::ops::Add<(i32, i32)> for Point {
+impl std::ops::Add<(i64, i64)> for Point {
     type Output = Self;
 
-    fn add(self, rhs: (i32, i32)) -> Self::Output {
-        Self {
-            x: self.x + rhs.0,
-            y: self.y + rhs.1,
-        }
+    fn add(self, rhs: (i64, i64)) -> Self::Output {
+        Self::new(self.x + rhs.0, self.y + rhs.1)
+    }
+}
+
+impl Display for Point {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
     }
 }
 
-fn parse_int(input: &str) -> IResult<&str, i32> {
-    map_res(digit1, str::parse).parse(input)
+fn parse_int(input: &str) -> IResult<&str, i64> {
+    nom::character::complete::i64(input)
 }
 
-fn parse_point(input: &str) -> IResult<&str, Point> {
-    map(separated_pair(parse_int, char(','), parse_int), Point::from).parse(input)
+/// The `x` and `y` of a point before they're checked to be in range.
+type Coordinates = (i64, i64);
+
+fn parse_point(input: &str) -> IResult<&str, Coordinates> {
+    separated_pair(parse_int, delimited(space0, char(','), space0), parse_int).parse(input)
+}
+
+impl FromStr for Point {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match terminated(preceded(space0, parse_point), space0)
+            .parse(s)
+            .finish()
+        {
+            Ok(("", point)) => Point::try_from(point),
+            _ => bail!("Failed to parse"),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -59,17 +96,235 @@ pub struct Line {
     end: Point,
 }
 
+/// How a line is turned into grid cells.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Raster {
+    /// Only the points with integer coordinates that lie exactly on the line. A line from `0,0` to
+    /// `4,2` covers `0,0`, `2,1` and `4,2`.
+    #[default]
+    Lattice,
+    /// Every cell Bresenham's algorithm visits walking from the start to the end, so the cells
+    /// form an unbroken path. A line from `0,0` to `4,2` covers `0,0`, `1,1`, `2,1`, `3,2` and
+    /// `4,2`, with cells exactly halfway between two rows rounded towards the end.
+    Bresenham,
+}
+
 impl Line {
-    fn gradient(&self) -> (i32, i32) {
+    /// Step between consecutive lattice points on the line, or `(0, 0)` if the line is a single
+    /// point.
+    fn gradient(&self) -> (i64, i64) {
         let gradient = (self.end.x - self.start.x, self.end.y - self.start.y);
         let hcf = num::integer::gcd(gradient.0, gradient.1);
+        if hcf == 0 {
+            return (0, 0);
+        }
 
         (gradient.0 / hcf, gradient.1 / hcf)
     }
 
-    fn iter(&self) -> LineIter {
+    /// True if both ends have the same y coordinate, including a line that's a single point.
+    pub fn is_horizontal(&self) -> bool {
+        self.start.y == self.end.y
+    }
+
+    /// True if both ends have the same x coordinate, including a line that's a single point.
+    pub fn is_vertical(&self) -> bool {
+        self.start.x == self.end.x
+    }
+
+    /// True if the line is at 45 degrees.
+    pub fn is_diagonal(&self) -> bool {
+        let (dx, dy) = (self.end.x - self.start.x, self.end.y - self.start.y);
+        dx != 0 && dx.abs() == dy.abs()
+    }
+
+    fn iter(&self) -> LineIter<'_> {
         self.into_iter()
     }
+
+    /// The cells covered by the line in the given mode. Both modes agree for horizontal, vertical
+    /// and diagonal lines.
+    pub fn points(&self, raster: Raster) -> Points<'_> {
+        match raster {
+            Raster::Lattice => Points::Lattice(self.iter()),
+            Raster::Bresenham => Points::Bresenham(Bresenham::new(self)),
+        }
+    }
+
+    /// True if `point` is one of the cells the line covers in the given mode.
+    pub fn covers(&self, point: Point, raster: Raster) -> bool {
+        self.covers_any(point, point, raster)
+    }
+
+    /// True if the line covers at least one cell of the rectangle from `min` to `max`, edges
+    /// included, in the given mode. Takes the same time however long the line is.
+    fn covers_any(&self, min: Point, max: Point, raster: Raster) -> bool {
+        let (x, y, steps) = self.axes(raster);
+        let (first_x, last_x) = x.steps_between(min.x as i128, max.x as i128);
+        let (first_y, last_y) = y.steps_between(min.y as i128, max.y as i128);
+
+        first_x.max(first_y).max(0) <= last_x.min(last_y).min(steps)
+    }
+
+    /// How each coordinate of the cells covered in the given mode moves with the number of steps
+    /// from the start, along with the number of steps to the end.
+    fn axes(&self, raster: Raster) -> (Axis, Axis, i128) {
+        match raster {
+            Raster::Lattice => {
+                let (gx, gy) = self.gradient();
+                let steps = match (gx, gy) {
+                    (0, 0) => 0,
+                    (0, _) => (self.end.y - self.start.y) / gy,
+                    _ => (self.end.x - self.start.x) / gx,
+                };
+                let axis = |start: i64, step: i64| Axis {
+                    start: start as i128,
+                    sign: step.signum() as i128,
+                    rise: step.abs() as i128,
+                    run: 1,
+                };
+
+                (
+                    axis(self.start.x, gx),
+                    axis(self.start.y, gy),
+                    steps as i128,
+                )
+            }
+            Raster::Bresenham => {
+                let (dx, dy) = (
+                    self.end.x as i128 - self.start.x as i128,
+                    self.end.y as i128 - self.start.y as i128,
+                );
+                // the longer axis moves one cell every step
+                let steps = dx.abs().max(dy.abs());
+                let axis = |start: i64, delta: i128| Axis {
+                    start: start as i128,
+                    sign: delta.signum(),
+                    rise: delta.abs(),
+                    run: steps.max(1),
+                };
+
+                (axis(self.start.x, dx), axis(self.start.y, dy), steps)
+            }
+        }
+    }
+}
+
+/// A coordinate of the cells along a line after some number of steps from the start,
+/// `start + sign * floor((2 * steps * rise + run) / (2 * run))`. That's `rise / run` cells a step
+/// rounded half up, which is what `Bresenham` does along the shorter axis.
+#[derive(Debug, Copy, Clone)]
+struct Axis {
+    start: i128,
+    sign: i128,
+    rise: i128,
+    run: i128,
+}
+
+impl Axis {
+    /// The first and last number of steps where the coordinate is from `min` to `max`, the first
+    /// is after the last if there are none.
+    fn steps_between(&self, min: i128, max: i128) -> (i128, i128) {
+        // how far from the start the coordinate has to move
+        let (low, high) = if self.sign < 0 {
+            (self.start - max, self.start - min)
+        } else {
+            (min - self.start, max - self.start)
+        };
+        if self.rise == 0 {
+            return if low <= 0 && 0 <= high {
+                (0, i128::MAX)
+            } else {
+                (1, 0)
+            };
+        }
+
+        // floor((2 * steps * rise + run) / (2 * run)) >= low, rounding the bound up
+        let first = -(self.run - 2 * self.run * low).div_euclid(2 * self.rise);
+        // floor((2 * steps * rise + run) / (2 * run)) <= high, rounding the bound down
+        let last = (2 * self.run * (high + 1) - self.run - 1).div_euclid(2 * self.rise);
+
+        (first, last)
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.start, self.end)
+    }
+}
+
+pub enum Points<'a> {
+    Lattice(LineIter<'a>),
+    Bresenham(Bresenham),
+}
+
+impl<'a> Iterator for Points<'a> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Points::Lattice(iter) => iter.next(),
+            Points::Bresenham(iter) => iter.next(),
+        }
+    }
+}
+
+pub struct Bresenham {
+    finished: bool,
+    current: Point,
+    end: Point,
+    /// Absolute change in x along the line, and negated absolute change in y.
+    delta: (i64, i64),
+    step: (i64, i64),
+    error: i64,
+}
+
+impl Bresenham {
+    fn new(line: &Line) -> Self {
+        let dx = (line.end.x - line.start.x).abs();
+        let dy = -(line.end.y - line.start.y).abs();
+
+        Self {
+            finished: false,
+            current: line.start,
+            end: line.end,
+            delta: (dx, dy),
+            step: (
+                (line.end.x - line.start.x).signum(),
+                (line.end.y - line.start.y).signum(),
+            ),
+            error: dx + dy,
+        }
+    }
+}
+
+impl Iterator for Bresenham {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let point = self.current;
+        if point == self.end {
+            self.finished = true;
+            return Some(point);
+        }
+
+        let error = 2 * self.error;
+        if error >= self.delta.1 {
+            self.error += self.delta.1;
+            self.current.x += self.step.0;
+        }
+        if error <= self.delta.0 {
+            self.error += self.delta.0;
+            self.current.y += self.step.1;
+        }
+
+        Some(point)
+    }
 }
 
 impl<'a> IntoIterator for &'a Line {
@@ -88,7 +343,7 @@ impl<'a> IntoIterator for &'a Line {
 
 pub struct LineIter<'a> {
     finished: bool,
-    gradient: (i32, i32),
+    gradient: (i64, i64),
     current: Point,
     line: &'a Line,
 }
@@ -101,13 +356,13 @@ impl<'a> Iterator for LineIter<'a> {
             return None;
         }
 
-        if self.current == self.line.end {
+        let point = self.current;
+        if point == self.line.end {
             self.finished = true;
+        } else {
+            self.current = point + self.gradient;
         }
 
-        let point = self.current;
-        self.current = self.current + self.gradient;
-
         Some(point)
     }
 }
@@ -121,13 +376,15 @@ impl From<(Point, Point)> for Line {
     }
 }
 
-fn parse_line(input: &str) -> IResult<&str, Line> {
-    map(
-        preceded(
-            multispace0,
-            separated_pair(parse_point, tag(" -> "), parse_point),
+fn parse_line(input: &str) -> IResult<&str, (Coordinates, Coordinates)> {
+    delimited(
+        multispace0,
+        separated_pair(
+            parse_point,
+            delimited(space0, tag("->"), space0),
+            parse_point,
         ),
-        Line::from,
+        space0,
     )
     .parse(input)
 }
@@ -137,7 +394,11 @@ impl FromStr for Line {
 
     fn from_str(s: &str) -> anyhow::Result<Self> {
         match parse_line(s).finish() {
-            Ok((_, line)) => Ok(line),
+            Ok(("", (start, end))) => Ok(Line {
+                start: Point::try_from(start)?,
+                end: Point::try_from(end)?,
+            }),
+            Ok((rest, _)) => bail!("Unexpected {:?} after line", rest),
             Err(_) => bail!("Failed to parse"),
         }
     }
@@ -147,34 +408,629 @@ pub struct Lines(Vec<Line>);
 
 impl Lines {
     pub fn dangerous_points_horizontal_or_vert(&self) -> usize {
-        let mut grid: HashMap<Point, u32> = HashMap::new();
-        for line in self
-            .0
+        count_rasterised(
+            self.0
+                .iter()
+                .filter(|line| line.is_horizontal() || line.is_vertical()),
+            Raster::Lattice,
+        )
+    }
+
+    pub fn dangerous_points_all(&self) -> usize {
+        self.dangerous_points(Raster::Lattice)
+    }
+
+    /// Number of cells covered by more than one line, with the lines rasterised in the given mode.
+    pub fn dangerous_points(&self, raster: Raster) -> usize {
+        count_rasterised(self.0.iter(), raster)
+    }
+
+    /// The top left and bottom right corners of the smallest rectangle containing every line, or
+    /// `None` if there are no lines.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let mut points = self.0.iter().flat_map(|line| [line.start, line.end]);
+        let first = points.next()?;
+
+        Some(points.fold((first, first), |(min, max), point| {
+            (
+                Point::new(min.x.min(point.x), min.y.min(point.y)),
+                Point::new(max.x.max(point.x), max.y.max(point.y)),
+            )
+        }))
+    }
+
+    /// Counts how many lines cover each cell in the bounding box of the lines, failing if the box
+    /// has more than `MAX_GRID_CELLS` cells.
+    pub fn overlap_grid(&self, raster: Raster) -> anyhow::Result<OverlapGrid> {
+        let (min, max) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => {
+                return Ok(OverlapGrid {
+                    origin: Point::new(0, 0),
+                    width: 0,
+                    height: 0,
+                    counts: Vec::new(),
+                })
+            }
+        };
+
+        // coordinates are within ±MAX_COORDINATE, so the product of the sides fits a u128
+        let width = (max.x - min.x) as u64 + 1;
+        let height = (max.y - min.y) as u64 + 1;
+        if width as u128 * height as u128 > MAX_GRID_CELLS as u128 {
+            bail!(
+                "the lines cover a {}x{} grid, it must have at most {} cells",
+                width,
+                height,
+                MAX_GRID_CELLS
+            );
+        }
+
+        let width = width as usize;
+        let height = height as usize;
+        let mut counts = vec![0; width * height];
+        for line in self.0.iter() {
+            for point in line.points(raster) {
+                counts[(point.y - min.y) as usize * width + (point.x - min.x) as usize] += 1;
+            }
+        }
+
+        Ok(OverlapGrid {
+            origin: min,
+            width,
+            height,
+            counts,
+        })
+    }
+
+    /// An index for finding the lines covering a point or a rectangle, with the lines rasterised
+    /// in the given mode.
+    pub fn spatial_index(&self, raster: Raster) -> SpatialIndex<'_> {
+        SpatialIndex::new(self, raster, DEFAULT_BUCKET_SIZE)
+    }
+
+    /// Same as `dangerous_points_horizontal_or_vert`, but counts the points without visiting each
+    /// one, see `count_overlaps`.
+    pub fn dangerous_points_horizontal_or_vert_analytic(&self) -> usize {
+        count_overlaps(
+            self.0
+                .iter()
+                .filter(|line| line.is_horizontal() || line.is_vertical()),
+        )
+    }
+
+    /// Same as `dangerous_points_all`, but counts the points without visiting each one, see
+    /// `count_overlaps`. Only lattice points are counted, as in `Raster::Lattice`.
+    pub fn dangerous_points_all_analytic(&self) -> usize {
+        count_overlaps(self.0.iter())
+    }
+}
+
+/// Number of lines covering each cell of a rectangle of the vent field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlapGrid {
+    /// The top left cell.
+    origin: Point,
+    width: usize,
+    height: usize,
+    /// Counts in row-major order.
+    counts: Vec<u32>,
+}
+
+impl OverlapGrid {
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of lines covering `point`, 0 if it's outside the grid.
+    pub fn get(&self, point: Point) -> u32 {
+        let (x, y) = (point.x - self.origin.x, point.y - self.origin.y);
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return 0;
+        }
+
+        self.counts[y as usize * self.width + x as usize]
+    }
+
+    pub fn max(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Binary greyscale PGM, scaled so the most overlapped cell is white.
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let max = self.max().max(1) as u64;
+        let mut image = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        image.extend(
+            self.counts
+                .iter()
+                .map(|&count| (count as u64 * 255 / max) as u8),
+        );
+
+        image
+    }
+
+    /// Binary colour PPM, running from black for no lines through blue and red to white for the
+    /// most overlapped cell.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let max = self.max();
+        let mut image = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for &count in self.counts.iter() {
+            image.extend(heat(count, max));
+        }
+
+        image
+    }
+
+    /// The path between two cells of the grid, moving up, down, left or right, that passes through
+    /// the fewest lines, counting a cell covered by two lines twice. Ties go to the shortest path.
+    /// `None` if either cell is outside the grid.
+    pub fn safest_path(&self, from: Point, to: Point) -> Option<SafePath> {
+        let index = |point: Point| {
+            let (x, y) = (point.x - self.origin.x, point.y - self.origin.y);
+            (x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height)
+                .then(|| y as usize * self.width + x as usize)
+        };
+        let point = |i: usize| {
+            Point::new(
+                self.origin.x + (i % self.width) as i64,
+                self.origin.y + (i / self.width) as i64,
+            )
+        };
+        let (start, goal) = (index(from)?, index(to)?);
+
+        // Dijkstra's algorithm on (danger, steps)
+        let mut best: Vec<Option<(u64, usize)>> = vec![None; self.counts.len()];
+        let mut previous = vec![usize::MAX; self.counts.len()];
+        let mut queue = BinaryHeap::new();
+        best[start] = Some((self.counts[start] as u64, 0));
+        queue.push(Reverse((self.counts[start] as u64, 0, start)));
+        while let Some(Reverse((danger, steps, cell))) = queue.pop() {
+            if cell == goal {
+                break;
+            }
+            if best[cell] != Some((danger, steps)) {
+                continue;
+            }
+
+            let current = point(cell);
+            for next in [(0, -1), (-1, 0), (1, 0), (0, 1)]
+                .into_iter()
+                .filter_map(|step| index(current + step))
+            {
+                let cost = (danger + self.counts[next] as u64, steps + 1);
+                if best[next].is_none_or(|best| cost < best) {
+                    best[next] = Some(cost);
+                    previous[next] = cell;
+                    queue.push(Reverse((cost.0, cost.1, next)));
+                }
+            }
+        }
+
+        let (danger, _) = best[goal]?;
+        let mut cells = vec![to];
+        let mut cell = goal;
+        while cell != start {
+            cell = previous[cell];
+            cells.push(point(cell));
+        }
+        cells.reverse();
+
+        Some(SafePath { cells, danger })
+    }
+}
+
+/// A path through the vent field, see `OverlapGrid::safest_path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafePath {
+    /// Every cell on the path, including both ends.
+    pub cells: Vec<Point>,
+    /// The total number of lines covering the cells on the path.
+    pub danger: u64,
+}
+
+/// Width and height in cells of the smallest buckets in a `SpatialIndex` from
+/// `Lines::spatial_index`.
+pub const DEFAULT_BUCKET_SIZE: i64 = 16;
+
+/// Lines bucketed by the square areas of the field they cover, for finding the lines covering a
+/// point or a rectangle without checking every line.
+pub struct SpatialIndex<'a> {
+    lines: &'a [Line],
+    raster: Raster,
+    bucket_size: i64,
+    /// Indices of the lines covering a cell in each bucket, keyed by the bucket's column and row
+    /// counted from the origin, for buckets `bucket_size << level` wide at each level. A line is
+    /// only in the first level where it's shorter than a bucket, so it's in at most four buckets
+    /// however long it is. Only buckets some line covers are stored.
+    levels: Vec<HashMap<(i64, i64), Vec<usize>>>,
+}
+
+impl<'a> SpatialIndex<'a> {
+    /// The smallest buckets are `bucket_size` square.
+    ///
+    /// Panics if `bucket_size` isn't positive.
+    pub fn new(lines: &'a Lines, raster: Raster, bucket_size: i64) -> Self {
+        assert!(bucket_size > 0, "bucket size must be positive");
+
+        let mut index = Self {
+            lines: &lines.0,
+            raster,
+            bucket_size,
+            levels: Vec::new(),
+        };
+
+        for (i, line) in lines.0.iter().enumerate() {
+            let length = (line.end.x as i128 - line.start.x as i128)
+                .abs()
+                .max((line.end.y as i128 - line.start.y as i128).abs());
+            let mut level = 0;
+            while index.size(level) as i128 <= length && index.size(level) < i64::MAX {
+                level += 1;
+            }
+            if index.levels.len() <= level {
+                index.levels.resize_with(level + 1, HashMap::new);
+            }
+
+            let min = Point::new(line.start.x.min(line.end.x), line.start.y.min(line.end.y));
+            let max = Point::new(line.start.x.max(line.end.x), line.start.y.max(line.end.y));
+            let (first, last) = (index.bucket(min, level), index.bucket(max, level));
+            for column in first.0..=last.0 {
+                for row in first.1..=last.1 {
+                    // the part of the bucket the line's bounding box overlaps
+                    let size = index.size(level) as i128;
+                    let clip = |bucket: i64, min: i64, max: i64| {
+                        let start = bucket as i128 * size;
+                        (
+                            start.max(min as i128) as i64,
+                            (start + size - 1).min(max as i128) as i64,
+                        )
+                    };
+                    let (left, right) = clip(column, min.x, max.x);
+                    let (top, bottom) = clip(row, min.y, max.y);
+                    if line.covers_any(Point::new(left, top), Point::new(right, bottom), raster) {
+                        index.levels[level]
+                            .entry((column, row))
+                            .or_default()
+                            .push(i);
+                    }
+                }
+            }
+        }
+
+        index
+    }
+
+    /// Width and height of the buckets at `level`.
+    fn size(&self, level: usize) -> i64 {
+        if level >= 63 {
+            return i64::MAX;
+        }
+        self.bucket_size.saturating_mul(1 << level)
+    }
+
+    /// The column and row of the bucket at `level` containing `point`.
+    fn bucket(&self, point: Point, level: usize) -> (i64, i64) {
+        let size = self.size(level);
+        (point.x.div_euclid(size), point.y.div_euclid(size))
+    }
+
+    /// The lines covering `point`, in input order.
+    pub fn at(&self, point: Point) -> Vec<&'a Line> {
+        let mut candidates: Vec<usize> = self
+            .levels
             .iter()
-            .filter(|line| matches!(line.gradient(), (0, 1) | (1, 0) | (-1, 0) | (0, -1)))
-        {
-            for point in line.iter() {
-                let entry = grid.entry(point).or_insert(0);
-                *entry += 1;
+            .enumerate()
+            .filter_map(|(level, buckets)| buckets.get(&self.bucket(point, level)))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+
+        candidates
+            .into_iter()
+            .map(|i| &self.lines[i])
+            .filter(|line| line.covers(point, self.raster))
+            .collect()
+    }
+
+    /// The lines covering at least one cell of the rectangle with the given opposite corners,
+    /// edges included, in input order.
+    pub fn within(&self, corner: Point, opposite: Point) -> Vec<&'a Line> {
+        let min = Point::new(corner.x.min(opposite.x), corner.y.min(opposite.y));
+        let max = Point::new(corner.x.max(opposite.x), corner.y.max(opposite.y));
+
+        let mut candidates = Vec::new();
+        for (level, buckets) in self.levels.iter().enumerate() {
+            // the buckets overlapping the rectangle
+            let (first_column, first_row) = self.bucket(min, level);
+            let (last_column, last_row) = self.bucket(max, level);
+            let overlapping = (last_column as i128 - first_column as i128 + 1)
+                * (last_row as i128 - first_row as i128 + 1);
+
+            // look up each overlapping bucket, unless there are more of them than stored buckets
+            if overlapping <= buckets.len() as i128 {
+                candidates.extend(
+                    (first_row..=last_row)
+                        .flat_map(|y| (first_column..=last_column).map(move |x| (x, y)))
+                        .filter_map(|bucket| buckets.get(&bucket))
+                        .flatten(),
+                );
+            } else {
+                candidates.extend(
+                    buckets
+                        .iter()
+                        .filter(|((x, y), _)| {
+                            (first_column..=last_column).contains(x)
+                                && (first_row..=last_row).contains(y)
+                        })
+                        .flat_map(|(_, lines)| lines.iter()),
+                );
             }
         }
+        candidates.sort_unstable();
+        candidates.dedup();
 
-        grid.iter().filter(|(_, count)| **count > 1).count()
+        candidates
+            .into_iter()
+            .map(|&i| &self.lines[i])
+            .filter(|line| line.covers_any(min, max, self.raster))
+            .collect()
     }
+}
 
-    pub fn dangerous_points_all(&self) -> usize {
-        let mut grid: HashMap<Point, u32> = HashMap::new();
-        for line in self.0.iter() {
-            for point in line.iter() {
-                let entry = grid.entry(point).or_insert(0);
-                *entry += 1;
+/// Colours from `heat` for no lines up to the most lines, evenly spaced.
+const HEAT_SCALE: [[u8; 3]; 5] = [
+    [0, 0, 0],
+    [0, 0, 200],
+    [220, 0, 0],
+    [255, 210, 0],
+    [255, 255, 255],
+];
+
+/// Colour for a cell covered by `count` of at most `max` lines. Any line at all is at least the
+/// second colour so single lines stand out from empty cells.
+fn heat(count: u32, max: u32) -> [u8; 3] {
+    if count == 0 {
+        return HEAT_SCALE[0];
+    }
+    if max <= 1 {
+        return HEAT_SCALE[1];
+    }
+
+    // position between the second colour (count 1) and the last (count max)
+    let t = (count - 1) as f64 / (max - 1) as f64 * (HEAT_SCALE.len() - 2) as f64;
+    let i = (t.floor() as usize + 1).min(HEAT_SCALE.len() - 2);
+    let fraction = t - (i - 1) as f64;
+    let (from, to) = (HEAT_SCALE[i], HEAT_SCALE[i + 1]);
+
+    [0, 1, 2].map(|c| (from[c] as f64 + (to[c] as f64 - from[c] as f64) * fraction).round() as u8)
+}
+
+impl Display for OverlapGrid {
+    /// Draws the grid as in the puzzle, `.` for no lines, the count for up to 9 lines and `+` for
+    /// more.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.counts.chunks(self.width.max(1)) {
+            for &count in row {
+                let c = match count {
+                    0 => '.',
+                    1..=9 => char::from_digit(count, 10).expect("count is a single digit"),
+                    _ => '+',
+                };
+                write!(f, "{}", c)?;
             }
+            writeln!(f)?;
         }
 
-        grid.iter().filter(|(_, count)| **count > 1).count()
+        Ok(())
     }
 }
 
+/// The infinite line through a segment, written as `b * x - a * y = offset` where `(a, b)` is the
+/// segment's direction reduced so that consecutive lattice points on the line are `(a, b)` apart.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Support {
+    direction: (i128, i128),
+    offset: i128,
+}
+
+impl Support {
+    fn of(line: &Line) -> Self {
+        let (dx, dy) = (
+            (line.end.x - line.start.x) as i128,
+            (line.end.y - line.start.y) as i128,
+        );
+        let hcf = num::integer::gcd(dx, dy);
+        // a single point lies on a horizontal line as well as any other
+        let (a, b) = if hcf == 0 {
+            (1, 0)
+        } else {
+            (dx / hcf, dy / hcf)
+        };
+        // point both directions along a line the same way so they share a support
+        let (a, b) = if a < 0 || (a == 0 && b < 0) {
+            (-a, -b)
+        } else {
+            (a, b)
+        };
+
+        Self {
+            direction: (a, b),
+            offset: b * line.start.x as i128 - a * line.start.y as i128,
+        }
+    }
+
+    /// Where a point lies along the line. Consecutive lattice points are `step` apart.
+    fn position(&self, (x, y): (i128, i128)) -> i128 {
+        x * self.direction.0 + y * self.direction.1
+    }
+
+    fn step(&self) -> i128 {
+        self.direction.0 * self.direction.0 + self.direction.1 * self.direction.1
+    }
+
+    /// The lattice point where two lines cross, if they cross at one.
+    fn intersection(&self, other: &Self) -> Option<(i128, i128)> {
+        let (a1, b1, c1) = (self.direction.0, self.direction.1, self.offset);
+        let (a2, b2, c2) = (other.direction.0, other.direction.1, other.offset);
+
+        let det = a1 * b2 - a2 * b1;
+        if det == 0 {
+            return None;
+        }
+
+        let x = a1 * c2 - a2 * c1;
+        let y = b1 * c2 - b2 * c1;
+        if x % det != 0 || y % det != 0 {
+            return None;
+        }
+
+        Some((x / det, y / det))
+    }
+}
+
+/// Lattice points on a supporting line covered by at least one and by at least two segments, as
+/// sorted, disjoint half-open ranges of positions.
+#[derive(Debug, Clone)]
+struct Coverage {
+    support: Support,
+    covered: Vec<(i128, i128)>,
+    overlapping: Vec<(i128, i128)>,
+}
+
+impl Coverage {
+    /// Sweeps along the line, tracking how many segments cover each stretch between endpoints.
+    fn new(support: Support, segments: &[&Line]) -> Self {
+        let step = support.step();
+        let mut events: Vec<(i128, i32)> = Vec::with_capacity(segments.len() * 2);
+        for line in segments {
+            let start = support.position((line.start.x as i128, line.start.y as i128));
+            let end = support.position((line.end.x as i128, line.end.y as i128));
+            events.push((start.min(end), 1));
+            events.push((start.max(end) + step, -1));
+        }
+        events.sort_unstable();
+
+        let mut covered = Vec::new();
+        let mut overlapping = Vec::new();
+        let mut depth = 0;
+        let mut previous = None;
+        for (position, delta) in events {
+            if let Some(previous) = previous {
+                if position > previous && depth >= 1 {
+                    extend(&mut covered, previous, position);
+                }
+                if position > previous && depth >= 2 {
+                    extend(&mut overlapping, previous, position);
+                }
+            }
+            depth += delta;
+            previous = Some(position);
+        }
+
+        Self {
+            support,
+            covered,
+            overlapping,
+        }
+    }
+
+    fn overlapping_count(&self) -> i128 {
+        let step = self.support.step();
+        self.overlapping
+            .iter()
+            .map(|(start, end)| (end - start) / step)
+            .sum()
+    }
+}
+
+/// Adds `[start, end)` to sorted ranges, merging it with the last range if they touch.
+fn extend(ranges: &mut Vec<(i128, i128)>, start: i128, end: i128) {
+    match ranges.last_mut() {
+        Some(last) if last.1 == start => last.1 = end,
+        _ => ranges.push((start, end)),
+    }
+}
+
+fn contains(ranges: &[(i128, i128)], position: i128) -> bool {
+    let i = ranges.partition_point(|&(_, end)| end <= position);
+    ranges.get(i).is_some_and(|&(start, _)| start <= position)
+}
+
+/// Counts the cells covered by at least two lines by visiting every cell of every line.
+fn count_rasterised<'a>(lines: impl Iterator<Item = &'a Line>, raster: Raster) -> usize {
+    let mut grid: HashMap<Point, u32> = HashMap::new();
+    for line in lines {
+        for point in line.points(raster) {
+            let entry = grid.entry(point).or_insert(0);
+            *entry += 1;
+        }
+    }
+
+    grid.iter().filter(|(_, count)| **count > 1).count()
+}
+
+/// Counts the lattice points covered by at least two segments without visiting every covered
+/// point.
+///
+/// Segments are grouped by the infinite line through them. Points covered twice by segments on
+/// the same line are found by sweeping along that line, and points covered by segments on
+/// different lines can only be where those lines cross, which are found pairwise. A point can be
+/// in both groups, so the crossings are used to correct for counting it more than once.
+fn count_overlaps<'a>(lines: impl Iterator<Item = &'a Line>) -> usize {
+    let mut supports: HashMap<Support, Vec<&Line>> = HashMap::new();
+    for line in lines {
+        supports.entry(Support::of(line)).or_default().push(line);
+    }
+    let coverages: Vec<Coverage> = supports
+        .into_iter()
+        .map(|(support, segments)| Coverage::new(support, &segments))
+        .collect();
+
+    // lines covering each crossing point
+    let mut crossings: HashMap<(i128, i128), Vec<usize>> = HashMap::new();
+    for (i, first) in coverages.iter().enumerate() {
+        for (j, second) in coverages.iter().enumerate().skip(i + 1) {
+            let point = match first.support.intersection(&second.support) {
+                Some(point) => point,
+                None => continue,
+            };
+            if contains(&first.covered, first.support.position(point))
+                && contains(&second.covered, second.support.position(point))
+            {
+                let lines = crossings.entry(point).or_default();
+                lines.push(i);
+                lines.push(j);
+            }
+        }
+    }
+
+    // each crossing counts once, less however many times it was counted as an overlap on one line
+    let mut count: i128 = coverages.iter().map(Coverage::overlapping_count).sum();
+    for (point, mut lines) in crossings {
+        lines.sort_unstable();
+        lines.dedup();
+        let counted = lines
+            .into_iter()
+            .filter(|&i| {
+                let coverage = &coverages[i];
+                contains(&coverage.overlapping, coverage.support.position(point))
+            })
+            .count();
+        count += 1 - counted as i128;
+    }
+
+    count as usize
+}
+
 impl From<Vec<Line>> for Lines {
     fn from(lines: Vec<Line>) -> Self {
         Self(lines)
@@ -184,6 +1040,8 @@ impl From<Vec<Line>> for Lines {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
 
     #[test]
     fn can_parse_line() {
@@ -196,6 +1054,60 @@ mod tests {
         assert_eq!(line.parse::<Line>().unwrap(), expected);
     }
 
+    #[test]
+    fn parse_signed_coordinates_and_whitespace() {
+        let expected = Line::from((Point::new(-3, 4), Point::new(5, -2)));
+
+        for line in ["-3,4 -> 5,-2", "-3,4->5,-2", "  -3 , 4   ->\t5,  -2  "] {
+            assert_eq!(line.parse::<Line>().unwrap(), expected);
+        }
+        assert_eq!(" -7 ,8 ".parse::<Point>().unwrap(), Point::new(-7, 8));
+
+        let huge: Line = "-1099511627776,0 -> 1099511627776,-5".parse().unwrap();
+        assert_eq!(huge.start, Point::new(-MAX_COORDINATE, 0));
+        assert_eq!(huge.end, Point::new(MAX_COORDINATE, -5));
+    }
+
+    #[test]
+    fn parse_invalid_lines() {
+        for line in [
+            "",
+            "1,2",
+            "1,2 -> 3",
+            "1,2 => 3,4",
+            "- 1,2 -> 3,4",
+            "1,2 -> 3,4 -> 5,6",
+            "1099511627777,0 -> 0,0",
+            "0,0 -> 0,-99999999999999999999",
+            "-9223372036854775808,0 -> 0,0",
+        ] {
+            assert!(line.parse::<Line>().is_err(), "{:?}", line);
+        }
+
+        let error = "0,0 -> 0,-1099511627777".parse::<Line>().unwrap_err();
+        assert!(error.to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn points_are_always_in_range() {
+        let m = MAX_COORDINATE;
+        assert_eq!(Point::try_from((-m, m)).unwrap(), Point::new(-m, m));
+        assert!(Point::try_from((m + 1, 0)).is_err());
+        assert!(Point::try_from((0, i64::MIN)).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn point_out_of_range() {
+        Point::new(0, -MAX_COORDINATE - 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn point_moved_out_of_range() {
+        let _ = Point::new(MAX_COORDINATE, 0) + (1, 0);
+    }
+
     #[test]
     fn iter_line_grad_is_1() {
         let line: Line = "0,0 -> 5,5".parse().unwrap();
@@ -223,6 +1135,207 @@ mod tests {
         assert_eq!(line_iter.next(), None);
     }
 
+    #[test]
+    fn iter_single_point() {
+        let line: Line = "3,4 -> 3,4".parse().unwrap();
+
+        assert_eq!(line.iter().collect::<Vec<_>>(), vec![Point::new(3, 4)]);
+        assert_eq!(
+            line.points(Raster::Bresenham).collect::<Vec<_>>(),
+            vec![Point::new(3, 4)]
+        );
+    }
+
+    #[test]
+    fn iter_lattice_points_only() {
+        let line: Line = "0,0 -> 4,2".parse().unwrap();
+
+        assert_eq!(
+            line.points(Raster::Lattice).collect::<Vec<_>>(),
+            vec![Point::new(0, 0), Point::new(2, 1), Point::new(4, 2)]
+        );
+    }
+
+    #[test]
+    fn iter_bresenham() {
+        let line: Line = "0,0 -> 4,2".parse().unwrap();
+        assert_eq!(
+            line.points(Raster::Bresenham).collect::<Vec<_>>(),
+            vec![
+                Point::new(0, 0),
+                Point::new(1, 1),
+                Point::new(2, 1),
+                Point::new(3, 2),
+                Point::new(4, 2)
+            ]
+        );
+
+        let line: Line = "2,5 -> 0,0".parse().unwrap();
+        let points: Vec<Point> = line.points(Raster::Bresenham).collect();
+        assert_eq!(points.len(), 6);
+        assert_eq!(points.first(), Some(&Point::new(2, 5)));
+        assert_eq!(points.last(), Some(&Point::new(0, 0)));
+    }
+
+    #[test]
+    fn bresenham_matches_lattice_for_straight_lines() {
+        for line in [
+            "0,9 -> 5,9",
+            "7,0 -> 7,4",
+            "8,0 -> 0,8",
+            "5,5 -> 8,2",
+            "6,4 -> 2,0",
+        ] {
+            let line: Line = line.parse().unwrap();
+
+            assert_eq!(
+                line.points(Raster::Bresenham).collect::<Vec<_>>(),
+                line.points(Raster::Lattice).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn classify() {
+        let horizontal: Line = "0,9 -> 5,9".parse().unwrap();
+        let vertical: Line = "7,0 -> 7,4".parse().unwrap();
+        let diagonal: Line = "8,0 -> 0,8".parse().unwrap();
+        let steep: Line = "0,0 -> 10,5".parse().unwrap();
+        let point: Line = "3,3 -> 3,3".parse().unwrap();
+
+        assert!(
+            horizontal.is_horizontal() && !horizontal.is_vertical() && !horizontal.is_diagonal()
+        );
+        assert!(!vertical.is_horizontal() && vertical.is_vertical() && !vertical.is_diagonal());
+        assert!(!diagonal.is_horizontal() && !diagonal.is_vertical() && diagonal.is_diagonal());
+        assert!(!steep.is_horizontal() && !steep.is_vertical() && !steep.is_diagonal());
+        assert!(point.is_horizontal() && point.is_vertical() && !point.is_diagonal());
+    }
+
+    #[test]
+    fn count_dangerous_points_with_points_and_steep_lines() {
+        let lines: Lines = vec![
+            "0,0 -> 4,2".parse().unwrap(),
+            "1,0 -> 1,3".parse().unwrap(),
+            "2,1 -> 2,1".parse().unwrap(),
+            "0,0 -> 0,0".parse().unwrap(),
+        ]
+        .into();
+
+        // 0,0 and 2,1
+        assert_eq!(lines.dangerous_points(Raster::Lattice), 2);
+        assert_eq!(lines.dangerous_points_all_analytic(), 2);
+        // 0,0, 1,1 and 2,1
+        assert_eq!(lines.dangerous_points(Raster::Bresenham), 3);
+        // the single points are both horizontal and vertical
+        assert_eq!(lines.dangerous_points_horizontal_or_vert(), 0);
+    }
+
+    const EXAMPLE: &str = "\
+        0,9 -> 5,9
+        8,0 -> 0,8
+        9,4 -> 3,4
+        2,2 -> 2,1
+        7,0 -> 7,4
+        6,4 -> 2,0
+        0,9 -> 2,9
+        3,4 -> 1,4
+        0,0 -> 8,8
+        5,5 -> 8,2";
+
+    fn example() -> Lines {
+        EXAMPLE
+            .lines()
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn overlap_grid_ascii() {
+        let grid = example().overlap_grid(Raster::Lattice).unwrap();
+        let expected = "\
+1.1....11.
+.111...2..
+..2.1.111.
+...1.2.2..
+.112313211
+...1.2....
+..1...1...
+.1.....1..
+1.......1.
+222111....
+";
+
+        assert_eq!(grid.to_string(), expected);
+        assert_eq!(grid.get(Point::new(4, 4)), 3);
+        assert_eq!(grid.get(Point::new(-1, 4)), 0);
+        assert_eq!(grid.max(), 3);
+    }
+
+    #[test]
+    fn overlap_grid_images() {
+        let grid = example().overlap_grid(Raster::Lattice).unwrap();
+
+        let pgm = grid.to_pgm();
+        let header = b"P5\n10 10\n255\n";
+        assert_eq!(&pgm[..header.len()], header);
+        assert_eq!(pgm.len(), header.len() + 100);
+        assert_eq!(pgm[header.len() + 4 * 10 + 4], 255);
+        assert_eq!(pgm[header.len() + 1], 0);
+        assert_eq!(pgm[header.len()], 85);
+
+        let ppm = grid.to_ppm();
+        let header = b"P6\n10 10\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 300);
+        let pixel = |x: usize, y: usize| {
+            let i = header.len() + (y * 10 + x) * 3;
+            [ppm[i], ppm[i + 1], ppm[i + 2]]
+        };
+        assert_eq!(pixel(1, 0), HEAT_SCALE[0]);
+        assert_eq!(pixel(0, 0), HEAT_SCALE[1]);
+        assert_eq!(pixel(4, 4), HEAT_SCALE[4]);
+    }
+
+    #[test]
+    fn overlap_grid_too_large() {
+        let lines: Lines = vec![
+            "0,0 -> 0,0".parse().unwrap(),
+            "100000000000,100000000000 -> 100000000000,100000000000"
+                .parse()
+                .unwrap(),
+        ]
+        .into();
+        assert!(lines.overlap_grid(Raster::Lattice).is_err());
+
+        let lines: Lines = vec![
+            "0,0 -> 8191,0".parse().unwrap(),
+            "0,8191 -> 0,8191".parse().unwrap(),
+        ]
+        .into();
+        let grid = lines.overlap_grid(Raster::Lattice).unwrap();
+        assert_eq!((grid.width(), grid.height()), (8192, 8192));
+        let lines: Lines = vec![
+            "0,0 -> 8192,0".parse().unwrap(),
+            "0,8191 -> 0,8191".parse().unwrap(),
+        ]
+        .into();
+        assert!(lines.overlap_grid(Raster::Lattice).is_err());
+    }
+
+    #[test]
+    fn heat_scale() {
+        assert_eq!(heat(0, 0), HEAT_SCALE[0]);
+        assert_eq!(heat(1, 1), HEAT_SCALE[1]);
+        assert_eq!(heat(1, 4), HEAT_SCALE[1]);
+        assert_eq!(heat(4, 4), HEAT_SCALE[4]);
+        assert_eq!(heat(3, 4), HEAT_SCALE[3]);
+        assert_eq!(heat(2, 4), HEAT_SCALE[2]);
+        assert_eq!(heat(2, 3), [238, 105, 0]);
+    }
+
     #[test]
     fn count_dangerous_points() {
         let lines = "\
@@ -246,5 +1359,332 @@ mod tests {
             .into();
 
         assert_eq!(lines.dangerous_points_horizontal_or_vert(), 5);
+        assert_eq!(lines.dangerous_points_horizontal_or_vert_analytic(), 5);
+        assert_eq!(lines.dangerous_points_all(), 12);
+        assert_eq!(lines.dangerous_points_all_analytic(), 12);
+    }
+
+    #[test]
+    fn analytic_counts_collinear_overlaps() {
+        // two overlapping runs on the same diagonal, crossed by a vertical line inside the overlap
+        let lines: Lines = vec![
+            "0,0 -> 6,6".parse().unwrap(),
+            "3,3 -> 9,9".parse().unwrap(),
+            "8,8 -> 4,4".parse().unwrap(),
+            "5,0 -> 5,9".parse().unwrap(),
+            "0,5 -> 9,5".parse().unwrap(),
+        ]
+        .into();
+
+        assert_eq!(
+            lines.dangerous_points_all_analytic(),
+            lines.dangerous_points_all()
+        );
+    }
+
+    /// 100 pseudo-random short lines of any slope in a 40 by 40 area.
+    fn generate_lines(rng: &mut ChaCha8Rng) -> Lines {
+        let mut next = |bound: i64| rng.gen_range(0..bound);
+
+        let lines: Vec<Line> = (0..100)
+            .map(|_| {
+                let start = Point::new(next(40), next(40));
+                let length = 1 + next(20);
+                let direction = match next(5) {
+                    0 => (1, 0),
+                    1 => (0, 1),
+                    2 => (1, 1),
+                    3 => (1, -1),
+                    // any other slope
+                    _ => (1 + next(3), next(7) - 3),
+                };
+                let end = Point::new(
+                    start.x + direction.0 * length * if next(2) == 0 { 1 } else { -1 },
+                    start.y + direction.1 * length,
+                );
+                Line::from((start, end))
+            })
+            .collect();
+
+        lines.into()
+    }
+
+    #[test]
+    fn analytic_matches_rasterised_on_generated_lines() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        for _ in 0..20 {
+            let lines = generate_lines(&mut rng);
+
+            assert_eq!(
+                lines.dangerous_points_horizontal_or_vert_analytic(),
+                lines.dangerous_points_horizontal_or_vert()
+            );
+            assert_eq!(
+                lines.dangerous_points_all_analytic(),
+                lines.dangerous_points_all()
+            );
+        }
+    }
+
+    #[test]
+    fn counts_agree_far_from_the_origin() {
+        let mut rng = ChaCha8Rng::seed_from_u64(50);
+        for (dx, dy) in [
+            (-MAX_COORDINATE + 100, MAX_COORDINATE - 100),
+            (MAX_COORDINATE - 100, -MAX_COORDINATE + 100),
+        ] {
+            let lines: Lines = generate_lines(&mut rng)
+                .0
+                .into_iter()
+                .map(|line| Line::from((line.start + (dx, dy), line.end + (dx, dy))))
+                .collect::<Vec<_>>()
+                .into();
+
+            assert_eq!(
+                lines.dangerous_points_all_analytic(),
+                lines.dangerous_points_all()
+            );
+            assert_eq!(
+                lines.dangerous_points_horizontal_or_vert_analytic(),
+                lines.dangerous_points_horizontal_or_vert()
+            );
+            assert_eq!(
+                lines.dangerous_points(Raster::Bresenham),
+                lines
+                    .overlap_grid(Raster::Bresenham)
+                    .unwrap()
+                    .counts
+                    .iter()
+                    .filter(|&&count| count > 1)
+                    .count()
+            );
+        }
+    }
+
+    #[test]
+    fn analytic_counts_lines_spanning_the_whole_range() {
+        let m = MAX_COORDINATE;
+        let lines: Lines = vec![
+            Line::from((Point::new(-m, -m), Point::new(m, m))),
+            Line::from((Point::new(-m, m), Point::new(m, -m))),
+            Line::from((Point::new(-m, 0), Point::new(m, 0))),
+            // overlaps the horizontal line by 11 points, one of them where the diagonals cross
+            Line::from((Point::new(-5, 0), Point::new(5, 0))),
+            // steep line ending on the first diagonal at 3,3
+            Line::from((Point::new(3 - 3 * m / 5, 3 - m), Point::new(3, 3))),
+        ]
+        .into();
+
+        assert_eq!(lines.dangerous_points_all_analytic(), 12);
+    }
+
+    #[test]
+    fn spatial_index_point_query() {
+        let lines = example();
+        let expected: Vec<Line> = vec![
+            "8,0 -> 0,8".parse().unwrap(),
+            "9,4 -> 3,4".parse().unwrap(),
+            "0,0 -> 8,8".parse().unwrap(),
+        ];
+
+        for index in [
+            lines.spatial_index(Raster::Lattice),
+            SpatialIndex::new(&lines, Raster::Lattice, 3),
+        ] {
+            assert_eq!(
+                index.at(Point::new(4, 4)),
+                expected.iter().collect::<Vec<_>>()
+            );
+            assert!(index.at(Point::new(1, 0)).is_empty());
+            assert!(index.at(Point::new(-5, 20)).is_empty());
+        }
+    }
+
+    #[test]
+    fn spatial_index_rectangle_query() {
+        let lines = example();
+        let index = SpatialIndex::new(&lines, Raster::Lattice, 3);
+
+        let found: Vec<String> = index
+            .within(Point::new(8, 1), Point::new(7, 0))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(found, vec!["8,0 -> 0,8", "7,0 -> 7,4"]);
+
+        assert_eq!(
+            index.within(Point::new(-10, -10), Point::new(20, 20)).len(),
+            10
+        );
+        assert!(index
+            .within(Point::new(-10, -10), Point::new(-1, 20))
+            .is_empty());
+    }
+
+    #[test]
+    fn spatial_index_lines_far_apart() {
+        let lines: Lines = vec![
+            "0,0 -> 3,3".parse().unwrap(),
+            "100000000000,-100000000000 -> 100000000002,-100000000000"
+                .parse()
+                .unwrap(),
+            "-1099511627776,1099511627776 -> -1099511627776,1099511627776"
+                .parse()
+                .unwrap(),
+        ]
+        .into();
+        let index = lines.spatial_index(Raster::Lattice);
+
+        assert_eq!(index.at(Point::new(2, 2)), vec![&lines.0[0]]);
+        assert_eq!(
+            index.at(Point::new(100_000_000_001, -100_000_000_000)),
+            vec![&lines.0[1]]
+        );
+        assert!(index.at(Point::new(50_000_000_000, 0)).is_empty());
+
+        let m = MAX_COORDINATE;
+        assert_eq!(index.within(Point::new(-m, -m), Point::new(m, m)).len(), 3);
+        assert_eq!(
+            index
+                .within(Point::new(1, -m), Point::new(m, -1))
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["100000000000,-100000000000 -> 100000000002,-100000000000"]
+        );
+        assert_eq!(
+            index.within(Point::new(3, 3), Point::new(-m, m)),
+            vec![&lines.0[0], &lines.0[2]]
+        );
+    }
+
+    #[test]
+    fn spatial_index_matches_overlap_grid_on_generated_lines() {
+        let mut rng = ChaCha8Rng::seed_from_u64(41);
+        for raster in [Raster::Lattice, Raster::Bresenham] {
+            for _ in 0..5 {
+                let lines = generate_lines(&mut rng);
+                let grid = lines.overlap_grid(raster).unwrap();
+                let index = SpatialIndex::new(&lines, raster, 7);
+                let origin = grid.origin();
+
+                for y in 0..grid.height() as i64 {
+                    for x in 0..grid.width() as i64 {
+                        let point = Point::new(origin.x + x, origin.y + y);
+                        assert_eq!(index.at(point).len() as u32, grid.get(point));
+                    }
+                }
+
+                for _ in 0..20 {
+                    let corner = Point::new(rng.gen_range(-30..70), rng.gen_range(-30..70));
+                    let opposite = corner + (rng.gen_range(-15..15), rng.gen_range(-15..15));
+                    let (xs, ys) = (
+                        corner.x.min(opposite.x)..=corner.x.max(opposite.x),
+                        corner.y.min(opposite.y)..=corner.y.max(opposite.y),
+                    );
+                    let expected: Vec<&Line> = lines
+                        .0
+                        .iter()
+                        .filter(|line| {
+                            line.points(raster)
+                                .any(|p| xs.contains(&p.x) && ys.contains(&p.y))
+                        })
+                        .collect();
+                    assert_eq!(index.within(corner, opposite), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spatial_index_long_lines() {
+        let m = MAX_COORDINATE;
+        let lines: Lines = vec![
+            Line::from((Point::new(0, 0), Point::new(100_000_000, 0))),
+            Line::from((Point::new(-m, -m), Point::new(m, m))),
+            // a shallow line with 2 cells a row, which only hits lattice points every other column
+            Line::from((Point::new(-m, 0), Point::new(m, m))),
+        ]
+        .into();
+
+        let index = lines.spatial_index(Raster::Lattice);
+        assert_eq!(index.at(Point::new(5, 5)), vec![&lines.0[1]]);
+        assert_eq!(index.at(Point::new(0, 0)), vec![&lines.0[0], &lines.0[1]]);
+        assert_eq!(index.at(Point::new(0, m / 2)), vec![&lines.0[2]]);
+        assert!(index.at(Point::new(1, m / 2)).is_empty());
+        assert_eq!(
+            index.within(Point::new(m / 2, 0), Point::new(m, -1)),
+            Vec::<&Line>::new()
+        );
+        assert_eq!(
+            index.within(Point::new(-m, 1), Point::new(-m + 2, 1)),
+            vec![&lines.0[2]]
+        );
+        assert_eq!(
+            index.within(Point::new(99_999_999, -3), Point::new(200_000_000, 3)),
+            vec![&lines.0[0]]
+        );
+
+        let index = lines.spatial_index(Raster::Bresenham);
+        // cells halfway between two rows round towards the end
+        assert_eq!(index.at(Point::new(-1, m / 2)), vec![&lines.0[2]]);
+        assert_eq!(index.at(Point::new(3, m / 2 + 2)), vec![&lines.0[2]]);
+        assert!(index.at(Point::new(1, m / 2)).is_empty());
+        assert_eq!(
+            index.within(Point::new(-m + 1, 1), Point::new(-m + 1, 1)),
+            vec![&lines.0[2]]
+        );
+    }
+
+    #[test]
+    fn safest_path_goes_around_lines() {
+        let lines: Lines = vec![
+            "1,0 -> 1,2".parse().unwrap(),
+            "3,3 -> 3,3".parse().unwrap(),
+            "0,4 -> 0,4".parse().unwrap(),
+        ]
+        .into();
+        let grid = lines.overlap_grid(Raster::Lattice).unwrap();
+
+        let path = grid
+            .safest_path(Point::new(0, 0), Point::new(2, 0))
+            .unwrap();
+        assert_eq!(path.danger, 0);
+        let expected = [
+            (0, 0),
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (1, 3),
+            (2, 3),
+            (2, 2),
+            (2, 1),
+            (2, 0),
+        ];
+        assert_eq!(path.cells, expected.map(|(x, y)| Point::new(x, y)));
+
+        assert_eq!(grid.safest_path(Point::new(0, 0), Point::new(5, 0)), None);
+    }
+
+    #[test]
+    fn safest_path_through_example() {
+        let grid = example().overlap_grid(Raster::Lattice).unwrap();
+        let path = grid
+            .safest_path(Point::new(0, 0), Point::new(9, 9))
+            .unwrap();
+
+        assert_eq!(path.cells.first(), Some(&Point::new(0, 0)));
+        assert_eq!(path.cells.last(), Some(&Point::new(9, 9)));
+        for pair in path.cells.windows(2) {
+            let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+            assert_eq!(dx.abs() + dy.abs(), 1);
+        }
+        assert_eq!(
+            path.danger,
+            path.cells.iter().map(|&p| grid.get(p) as u64).sum::<u64>()
+        );
+        // the start is on a line, and moving a cell at a time there's no way past the other
+        // diagonal without stepping on it
+        assert_eq!(path.danger, 2);
     }
 }
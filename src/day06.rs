@@ -1,6 +1,98 @@
+use std::ops::RemAssign;
 use std::str::FromStr;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
+use num::traits::{CheckedAdd, CheckedMul};
+use num::{BigUint, One, Zero};
+
+/// The life cycle of a species of lanternfish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Species {
+    /// Timer a fish goes back to after giving birth.
+    pub reset: u8,
+    /// Timer of a newborn fish.
+    pub newborn: u8,
+    /// Number of fish born at a time.
+    pub litter: u64,
+}
+
+impl Species {
+    /// The species from the puzzle.
+    pub const LANTERNFISH: Self = Self {
+        reset: 6,
+        newborn: 8,
+        litter: 1,
+    };
+
+    /// Average factor the population grows by each day in the long run. When every cycle length a
+    /// fish can go through shares a common factor, e.g. reset 1 and newborn 1, the daily factor
+    /// keeps oscillating around this average instead of settling on it.
+    ///
+    /// This is the dominant eigenvalue of the daily transition matrix. The fish giving birth on a
+    /// day are those that gave birth `reset + 1` days before plus a litter for each fish that did
+    /// `newborn + 1` days before, so it's the positive root of
+    /// `1 = x^-(reset + 1) + litter * x^-(newborn + 1)`, which the right hand side only crosses
+    /// once between 1 and `1 + litter`.
+    pub fn growth_rate(&self) -> f64 {
+        let f = |x: f64| {
+            x.powi(-(self.reset as i32 + 1))
+                + self.litter as f64 * x.powi(-(self.newborn as i32 + 1))
+        };
+
+        let (mut low, mut high) = (1.0, 1.0 + self.litter as f64);
+        for _ in 0..100 {
+            let middle = (low + high) / 2.0;
+            if f(middle) > 1.0 {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        (low + high) / 2.0
+    }
+
+    /// Number of distinct timer values, from 0 up to the longer of the two delays.
+    fn timers(&self) -> usize {
+        self.reset.max(self.newborn) as usize + 1
+    }
+}
+
+impl Default for Species {
+    fn default() -> Self {
+        Self::LANTERNFISH
+    }
+}
+
+/// Parses comma-separated timers, each of which must be one a fish of `species` can have. There
+/// must be at least one fish, and whitespace around the timers is ignored. Errors give the
+/// position of the fish they're about.
+fn parse_timers<'a>(
+    s: &'a str,
+    species: &'a Species,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<u8>> + 'a> {
+    let s = s.trim();
+    if s.is_empty() {
+        bail!("no fish in the school");
+    }
+
+    Ok(s.split(',').enumerate().map(move |(i, timer)| {
+        let timer = timer.trim();
+        let value: u8 = timer
+            .parse()
+            .map_err(|e| anyhow!("fish {}: invalid timer {:?}: {}", i + 1, timer, e))?;
+        if value as usize >= species.timers() {
+            bail!(
+                "fish {}: timer {} is out of range, it must be at most {}",
+                i + 1,
+                value,
+                species.timers() - 1
+            );
+        }
+
+        Ok(value)
+    }))
+}
 
 // Find proper solution below.
 // Part 1 was naively implemented and over-engineered. Leaving as a reminder to self to think about
@@ -9,14 +101,14 @@ use anyhow::anyhow;
 struct Lanternfish(u8);
 
 impl Lanternfish {
-    pub fn new() -> Self {
-        Self(8)
+    pub fn new(species: &Species) -> Self {
+        Self(species.newborn)
     }
 
     /// Returns true if counter has reached 0 and Lanternfish will reproduce
-    fn progress_day(&mut self) -> bool {
+    fn progress_day(&mut self, species: &Species) -> bool {
         if self.0 == 0 {
-            self.0 = 6;
+            self.0 = species.reset;
             return true;
         }
 
@@ -28,20 +120,34 @@ impl Lanternfish {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct School {
     school: Vec<Lanternfish>,
+    species: Species,
 }
 
 impl School {
+    /// Parses a school of the given species from comma-separated timers, see `parse_timers`.
+    pub fn parse_with(s: &str, species: Species) -> anyhow::Result<Self> {
+        let school = parse_timers(s, &species)?
+            .map(|timer| timer.map(Lanternfish))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self { school, species })
+    }
+
+    pub fn species(&self) -> Species {
+        self.species
+    }
+
     fn progress_day(&mut self) {
-        let mut new_fish: u32 = 0;
+        let mut new_fish: u64 = 0;
 
         for fish in self.school.iter_mut() {
-            if fish.progress_day() {
-                new_fish += 1;
+            if fish.progress_day(&self.species) {
+                new_fish += self.species.litter;
             }
         }
 
         for _ in 0..new_fish {
-            self.school.push(Lanternfish::new());
+            self.school.push(Lanternfish::new(&self.species));
         }
     }
 
@@ -59,27 +165,55 @@ impl FromStr for School {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> anyhow::Result<Self> {
-        let school: Vec<Lanternfish> = s
-            .split(',')
-            .map(str::parse::<u8>)
-            .map(|r| r.map_err(|e| anyhow!(e)).map(Lanternfish))
-            .collect::<anyhow::Result<Vec<_>>>()?;
-
-        Ok(Self { school })
+        Self::parse_with(s, Species::LANTERNFISH)
     }
 }
 
-// Non-naive implementation that represents the school as 9 buckets with each bucket containing
-// the number of Lanternfish scheduled to reproduce after x days.
+// Non-naive implementation that represents the school as buckets, 9 for the puzzle's species,
+// with each bucket containing the number of Lanternfish scheduled to reproduce after x days.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NonNaiveSchool {
-    school: [u64; 9],
+    school: Vec<u64>,
+    species: Species,
 }
 
 impl NonNaiveSchool {
+    /// Parses a school of the given species from comma-separated timers, see `parse_timers`.
+    pub fn parse_with(s: &str, species: Species) -> anyhow::Result<Self> {
+        let mut school = vec![0; species.timers()];
+        for days_until_reprod in parse_timers(s, &species)? {
+            school[days_until_reprod? as usize] += 1;
+        }
+
+        Ok(Self { school, species })
+    }
+
+    pub fn species(&self) -> Species {
+        self.species
+    }
+
     fn progress_day(&mut self) {
+        let reproducing = self.school[0];
         self.school.rotate_left(1);
-        self.school[6] += self.school[8];
+        *self.school.last_mut().expect("at least one bucket") = 0;
+        self.school[self.species.reset as usize] += reproducing;
+        self.school[self.species.newborn as usize] += reproducing * self.species.litter;
+    }
+
+    /// Same as `progress_day`, but leaves the school as it is and returns `None` if a bucket
+    /// would overflow.
+    fn checked_progress_day(&mut self) -> Option<()> {
+        let reproducing = self.school[0];
+        let mut school = self.school.clone();
+        school.rotate_left(1);
+        *school.last_mut().expect("at least one bucket") = 0;
+        let reset = &mut school[self.species.reset as usize];
+        *reset = reset.checked_add(reproducing)?;
+        let newborn = &mut school[self.species.newborn as usize];
+        *newborn = newborn.checked_add(reproducing.checked_mul(self.species.litter)?)?;
+
+        self.school = school;
+        Some(())
     }
 
     pub fn progress(&mut self, days: usize) -> u64 {
@@ -89,25 +223,314 @@ impl NonNaiveSchool {
 
         self.school.iter().sum()
     }
+
+    /// The population on each day, starting with today, until the total no longer fits in a
+    /// `u64`.
+    pub fn days(&self) -> Days {
+        Days {
+            school: Some(self.clone()),
+            day: 0,
+        }
+    }
+
+    /// The first day on which there are more than `threshold` fish, counting today as day 0.
+    /// `None` if the population never gets that big, or not before overflowing a `u64`.
+    pub fn first_day_exceeding(&self, threshold: u64) -> Option<u64> {
+        let total: u64 = self.school.iter().sum();
+        if total == 0 || self.species.litter == 0 {
+            // the population never changes
+            return (total > threshold).then_some(0);
+        }
+
+        self.days()
+            .find(|population| population.total > threshold)
+            .map(|population| population.day)
+    }
+
+    /// The first `days + 1` days of `days` as CSV, with a column for the total and one for each
+    /// timer. Stops early if the population overflows.
+    pub fn to_csv(&self, days: u64) -> String {
+        let mut csv = String::from("day,total");
+        for timer in 0..self.school.len() {
+            csv.push_str(&format!(",timer_{}", timer));
+        }
+        csv.push('\n');
+
+        for population in self.days().take_while(|population| population.day <= days) {
+            csv.push_str(&format!("{},{}", population.day, population.total));
+            for count in population.buckets {
+                csv.push_str(&format!(",{}", count));
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// Size of the school after `days` days, without changing the school. Takes O(log days) steps
+    /// by raising the day's transition matrix to the power of `days`, but the result has about
+    /// `days * log10(Species::growth_rate)` digits, so millions of days already take seconds.
+    pub fn population_after(&self, days: u64) -> BigUint {
+        self.population(days, None)
+    }
+
+    /// Same as `population_after`, but modulo `modulus`, for numbers of days where the exact size
+    /// is too big to hold.
+    ///
+    /// Panics if `modulus` is 0.
+    pub fn population_after_mod(&self, days: u64, modulus: u64) -> u64 {
+        assert!(modulus > 0, "modulus must be positive");
+
+        let population = self.population(days, Some(&BigUint::from(modulus)));
+        u64::try_from(population).expect("less than a u64 modulus")
+    }
+
+    fn population(&self, days: u64, modulus: Option<&BigUint>) -> BigUint {
+        let power = Matrix::<BigUint>::transition(&self.species)
+            .and_then(|transition| transition.pow(days, modulus))
+            .expect("BigUint arithmetic never fails");
+
+        let mut population = BigUint::zero();
+        for row in power.0.iter() {
+            for (cell, &count) in row.iter().zip(self.school.iter()) {
+                population += cell * count;
+            }
+        }
+
+        match modulus {
+            Some(modulus) => population % modulus,
+            None => population,
+        }
+    }
+}
+
+/// The number of fish a single fish with each timer value becomes after a fixed number of days.
+/// Every fish with the same timer has the same descendants, so the size of any school of the same
+/// species after that many days is its bucket counts weighted by the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescendantTable {
+    species: Species,
+    days: u64,
+    table: Vec<u64>,
+}
+
+impl DescendantTable {
+    /// Builds the table in O(log days) steps. Fails if a single fish has too many descendants to
+    /// count in a `u64`.
+    pub fn new(species: Species, days: u64) -> anyhow::Result<Self> {
+        let overflow = || anyhow!("a fish has too many descendants after {} days", days);
+        let power = match days {
+            0 => Matrix::identity(species.timers()),
+            _ => Matrix::<u64>::transition(&species)
+                .and_then(|transition| transition.pow(days, None))
+                .ok_or_else(overflow)?,
+        };
+
+        // the column for a timer is where a single fish with that timer ends up
+        let table = (0..species.timers())
+            .map(|timer| {
+                power
+                    .0
+                    .iter()
+                    .try_fold(0u64, |total, row| total.checked_add(row[timer]))
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "a fish with timer {} has too many descendants after {} days",
+                            timer,
+                            days
+                        )
+                    })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            species,
+            days,
+            table,
+        })
+    }
+
+    pub fn species(&self) -> Species {
+        self.species
+    }
+
+    pub fn days(&self) -> u64 {
+        self.days
+    }
+
+    /// Descendants of a single fish with each timer value.
+    pub fn table(&self) -> &[u64] {
+        &self.table
+    }
+
+    /// Size of `school` after the table's number of days, without simulating it. Fails if the
+    /// school is of a different species or the size overflows a `u64`.
+    pub fn population(&self, school: &NonNaiveSchool) -> anyhow::Result<u64> {
+        if school.species != self.species {
+            bail!(
+                "table is for {:?}, but the school is {:?}",
+                self.species,
+                school.species
+            );
+        }
+
+        self.table
+            .iter()
+            .zip(school.school.iter())
+            .try_fold(0u64, |total, (&descendants, &count)| {
+                total.checked_add(descendants.checked_mul(count)?)
+            })
+            .ok_or_else(|| anyhow!("population overflows after {} days", self.days))
+    }
+}
+
+/// The size of a school on one day, see `NonNaiveSchool::days`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Population {
+    pub day: u64,
+    pub total: u64,
+    /// Number of fish with each timer value.
+    pub buckets: Vec<u64>,
+}
+
+pub struct Days {
+    /// `None` once the population has overflowed.
+    school: Option<NonNaiveSchool>,
+    day: u64,
+}
+
+impl Iterator for Days {
+    type Item = Population;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let school = self.school.as_mut()?;
+        let total = school
+            .school
+            .iter()
+            .try_fold(0u64, |total, &count| total.checked_add(count));
+        let total = match total {
+            Some(total) => total,
+            None => {
+                self.school = None;
+                return None;
+            }
+        };
+
+        let population = Population {
+            day: self.day,
+            total,
+            buckets: school.school.clone(),
+        };
+        if school.checked_progress_day().is_some() {
+            self.day += 1;
+        } else {
+            // this day is fine but the next isn't
+            self.school = None;
+        }
+
+        Some(population)
+    }
+}
+
+/// What the cells of a `Matrix` can be. `BigUint` arithmetic never fails, `u64` arithmetic fails
+/// on overflow.
+trait Cell:
+    Clone + Zero + One + From<u64> + CheckedAdd + CheckedMul + for<'a> RemAssign<&'a Self>
+{
+}
+
+impl<T> Cell for T where
+    T: Clone + Zero + One + From<u64> + CheckedAdd + CheckedMul + for<'a> RemAssign<&'a T>
+{
+}
+
+/// Square matrix mapping a school's bucket counts on one day to the next day's.
+///
+/// Every fish is still around the next day, so the column sums of a power of the transition
+/// matrix never decrease with the exponent. Every cell met on the way to a power is at most a
+/// column sum of it, so if anything overflows, so do the power's column sums, and with `u64` cells
+/// the power can fail as soon as it happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Matrix<T>(Vec<Vec<T>>);
+
+impl<T: Cell> Matrix<T> {
+    fn identity(size: usize) -> Self {
+        Self(
+            (0..size)
+                .map(|i| {
+                    (0..size)
+                        .map(|j| if i == j { T::one() } else { T::zero() })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// The matrix for a day of `NonNaiveSchool::progress_day`: every bucket moves down one, and
+    /// the fish in bucket 0 go back to the reset timer and give birth to a litter each. Fails if
+    /// the reset and newborn timers are the same and a fish and its litter overflow.
+    fn transition(species: &Species) -> Option<Self> {
+        let size = species.timers();
+        let mut matrix = Self(vec![vec![T::zero(); size]; size]);
+        for i in 0..size - 1 {
+            matrix.0[i][i + 1] = T::one();
+        }
+        matrix.0[species.reset as usize][0] = T::one();
+        let newborn = &mut matrix.0[species.newborn as usize][0];
+        *newborn = newborn.checked_add(&T::from(species.litter))?;
+
+        Some(matrix)
+    }
+
+    fn multiply(&self, other: &Self, modulus: Option<&T>) -> Option<Self> {
+        let size = self.0.len();
+        let mut product = vec![vec![T::zero(); size]; size];
+        for (i, row) in product.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                for k in 0..size {
+                    *cell = cell.checked_add(&self.0[i][k].checked_mul(&other.0[k][j])?)?;
+                }
+                if let Some(modulus) = modulus {
+                    *cell %= modulus;
+                }
+            }
+        }
+
+        Some(Self(product))
+    }
+
+    /// Exponentiation by squaring.
+    fn pow(&self, mut exponent: u64, modulus: Option<&T>) -> Option<Self> {
+        let mut result = Self::identity(self.0.len());
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.multiply(&base, modulus)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.multiply(&base, modulus)?;
+            }
+        }
+
+        Some(result)
+    }
 }
 
 impl FromStr for NonNaiveSchool {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> anyhow::Result<Self> {
-        let mut school: [u64; 9] = [0; 9];
-        for c in s.split(',') {
-            let days_until_reprod: usize = c.parse()?;
-            school[days_until_reprod] += 1;
-        }
-
-        Ok(Self { school })
+        Self::parse_with(s, Species::LANTERNFISH)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num::ToPrimitive;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
 
     #[test]
     fn can_parse_school_fromstr() {
@@ -120,11 +543,71 @@ mod tests {
                 Lanternfish(4),
                 Lanternfish(5),
             ],
+            species: Species::LANTERNFISH,
         };
 
         assert_eq!(school.parse::<School>().unwrap(), expected);
     }
 
+    #[test]
+    fn parse_ignores_whitespace() {
+        let school: School = " 3, 4 ,3\n".parse().unwrap();
+        assert_eq!(
+            school.school,
+            vec![Lanternfish(3), Lanternfish(4), Lanternfish(3)]
+        );
+
+        let school: NonNaiveSchool = "3,4,3,1,2\n".parse().unwrap();
+        assert_eq!(school.school, [0, 1, 1, 2, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |s: &str| {
+            let school = s.parse::<School>().unwrap_err().to_string();
+            let non_naive = s.parse::<NonNaiveSchool>().unwrap_err().to_string();
+            assert_eq!(school, non_naive);
+            school
+        };
+
+        assert_eq!(error(""), "no fish in the school");
+        assert_eq!(error(" \n"), "no fish in the school");
+        assert_eq!(
+            error("3,4,-1,2"),
+            "fish 3: invalid timer \"-1\": invalid digit found in string"
+        );
+        assert_eq!(
+            error("3,,4"),
+            "fish 2: invalid timer \"\": cannot parse integer from empty string"
+        );
+        assert_eq!(
+            error("3,4,300"),
+            "fish 3: invalid timer \"300\": number too large to fit in target type"
+        );
+        assert_eq!(
+            error("1,9"),
+            "fish 2: timer 9 is out of range, it must be at most 8"
+        );
+    }
+
+    #[test]
+    fn parse_checks_timers_against_species() {
+        let species = Species {
+            reset: 2,
+            newborn: 4,
+            litter: 1,
+        };
+
+        assert!(NonNaiveSchool::parse_with("0,4", species).is_ok());
+        assert!(School::parse_with("0,4", species).is_ok());
+        let error = NonNaiveSchool::parse_with("0,5", species).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "fish 2: timer 5 is out of range, it must be at most 4"
+        );
+        assert!(School::parse_with("5", species).is_err());
+    }
+
     #[test]
     fn progress_by() {
         let mut school: School = "3,4,3,1,2".parse().unwrap();
@@ -148,4 +631,285 @@ mod tests {
 
         assert_eq!(size, 5934);
     }
+
+    #[test]
+    fn population_after_matches_progress() {
+        let school: NonNaiveSchool = "3,4,3,1,2".parse().unwrap();
+        assert_eq!(school.population_after(80), BigUint::from(5934u32));
+        assert_eq!(school.population_after(256), BigUint::from(26984457539u64));
+
+        let mut progressed = school.clone();
+        let mut expected = progressed.progress(0);
+        for days in 0..150 {
+            if days > 0 {
+                expected = progressed.progress(1);
+            }
+            assert_eq!(school.population_after(days), BigUint::from(expected));
+            assert_eq!(
+                school.population_after_mod(days, 1_000_003),
+                expected % 1_000_003
+            );
+        }
+    }
+
+    #[test]
+    fn species_agree_across_simulations() {
+        for species in [
+            Species::LANTERNFISH,
+            Species {
+                reset: 2,
+                newborn: 4,
+                litter: 1,
+            },
+            Species {
+                reset: 5,
+                newborn: 3,
+                litter: 2,
+            },
+            Species {
+                reset: 0,
+                newborn: 2,
+                litter: 1,
+            },
+            Species {
+                reset: 6,
+                newborn: 8,
+                litter: 0,
+            },
+        ] {
+            let input = "0,1,2,0";
+            let mut naive = School::parse_with(input, species).unwrap();
+            let mut bucketed = NonNaiveSchool::parse_with(input, species).unwrap();
+            let initial = bucketed.clone();
+
+            for day in 1..=20 {
+                let expected = naive.progress(1) as u64;
+                assert_eq!(bucketed.progress(1), expected, "{:?} day {}", species, day);
+                assert_eq!(initial.population_after(day), BigUint::from(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn litters_multiply_growth() {
+        let species = Species {
+            reset: 1,
+            newborn: 1,
+            litter: 2,
+        };
+        let mut school = NonNaiveSchool::parse_with("0", species).unwrap();
+
+        // every other day each fish has two young, all in step
+        assert_eq!(school.progress(1), 3);
+        assert_eq!(school.progress(1), 3);
+        assert_eq!(school.progress(1), 9);
+        assert_eq!(school.progress(2), 27);
+    }
+
+    #[test]
+    fn daily_populations() {
+        let school: NonNaiveSchool = "3,4,3,1,2".parse().unwrap();
+        let days: Vec<Population> = school.days().take(3).collect();
+
+        assert_eq!(
+            days,
+            vec![
+                Population {
+                    day: 0,
+                    total: 5,
+                    buckets: vec![0, 1, 1, 2, 1, 0, 0, 0, 0],
+                },
+                Population {
+                    day: 1,
+                    total: 5,
+                    buckets: vec![1, 1, 2, 1, 0, 0, 0, 0, 0],
+                },
+                Population {
+                    day: 2,
+                    total: 6,
+                    buckets: vec![1, 2, 1, 0, 0, 0, 1, 0, 1],
+                },
+            ]
+        );
+
+        for population in school.days().take(100) {
+            assert_eq!(
+                population.total,
+                school.clone().progress(population.day as usize)
+            );
+        }
+    }
+
+    #[test]
+    fn daily_populations_stop_before_overflowing() {
+        let school: NonNaiveSchool = "3,4,3,1,2".parse().unwrap();
+        let last = school.days().last().unwrap();
+
+        assert_eq!(BigUint::from(last.total), school.population_after(last.day));
+        assert!(school.population_after(last.day + 1) > BigUint::from(u64::MAX));
+    }
+
+    #[test]
+    fn growth_rate() {
+        let rate = Species::LANTERNFISH.growth_rate();
+        // 1 = x^-7 + x^-9
+        assert!((rate.powi(-7) + rate.powi(-9) - 1.0).abs() < 1e-12);
+
+        let school: NonNaiveSchool = "3,4,3,1,2".parse().unwrap();
+        let today = school.population_after(2000).to_f64().unwrap();
+        let tomorrow = school.population_after(2001).to_f64().unwrap();
+        assert!((tomorrow / today - rate).abs() < 1e-9);
+
+        let species = Species {
+            reset: 6,
+            newborn: 8,
+            litter: 0,
+        };
+        assert_eq!(species.growth_rate(), 1.0);
+    }
+
+    #[test]
+    fn first_day_exceeding() {
+        let school: NonNaiveSchool = "3,4,3,1,2".parse().unwrap();
+
+        assert_eq!(school.first_day_exceeding(4), Some(0));
+        assert_eq!(school.first_day_exceeding(5), Some(2));
+        assert_eq!(school.first_day_exceeding(5933), Some(80));
+        assert_eq!(school.first_day_exceeding(5934), Some(81));
+        assert_eq!(school.first_day_exceeding(u64::MAX), None);
+
+        let species = Species {
+            reset: 6,
+            newborn: 8,
+            litter: 0,
+        };
+        let school = NonNaiveSchool::parse_with("1,2", species).unwrap();
+        assert_eq!(school.first_day_exceeding(1), Some(0));
+        assert_eq!(school.first_day_exceeding(2), None);
+    }
+
+    #[test]
+    fn csv_export() {
+        let school: NonNaiveSchool = "3,4,3,1,2".parse().unwrap();
+        let expected = "\
+day,total,timer_0,timer_1,timer_2,timer_3,timer_4,timer_5,timer_6,timer_7,timer_8
+0,5,0,1,1,2,1,0,0,0,0
+1,5,1,1,2,1,0,0,0,0,0
+2,6,1,2,1,0,0,0,1,0,1
+";
+
+        assert_eq!(school.to_csv(2), expected);
+    }
+
+    #[test]
+    fn descendant_table() {
+        let table = DescendantTable::new(Species::LANTERNFISH, 18).unwrap();
+        // gives birth on days 1, 8 and 15, its first child on days 10 and 17, its second on day 17
+        assert_eq!(table.table()[0], 7);
+        // gives birth on days 9 and 16, its first child on day 18
+        assert_eq!(table.table()[8], 4);
+
+        let school: NonNaiveSchool = "3,4,3,1,2".parse().unwrap();
+        assert_eq!(table.population(&school).unwrap(), 26);
+        let table = DescendantTable::new(Species::LANTERNFISH, 256).unwrap();
+        assert_eq!(table.population(&school).unwrap(), 26984457539);
+    }
+
+    #[test]
+    fn descendant_table_matches_progress_on_generated_schools() {
+        let mut rng = ChaCha8Rng::seed_from_u64(46);
+        let mut next = |bound: u64| rng.gen_range(0..bound);
+
+        for species in [
+            Species::LANTERNFISH,
+            Species {
+                reset: 3,
+                newborn: 5,
+                litter: 2,
+            },
+        ] {
+            let table = DescendantTable::new(species, 100).unwrap();
+            for _ in 0..50 {
+                let timers: Vec<String> = (0..1 + next(30))
+                    .map(|_| next(species.timers() as u64).to_string())
+                    .collect();
+                let school = NonNaiveSchool::parse_with(&timers.join(","), species).unwrap();
+
+                assert_eq!(
+                    table.population(&school).unwrap(),
+                    school.clone().progress(100)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn descendant_table_errors() {
+        assert!(DescendantTable::new(Species::LANTERNFISH, 1000).is_err());
+        assert!(DescendantTable::new(Species::LANTERNFISH, 1 << 26).is_err());
+        assert!(DescendantTable::new(Species::LANTERNFISH, u64::MAX).is_err());
+
+        let species = Species {
+            reset: 3,
+            newborn: 3,
+            litter: u64::MAX,
+        };
+        assert_eq!(
+            DescendantTable::new(species, 0).unwrap().table(),
+            [1, 1, 1, 1]
+        );
+        assert!(DescendantTable::new(species, 1).is_err());
+
+        let table = DescendantTable::new(Species::LANTERNFISH, 10).unwrap();
+        let species = Species {
+            reset: 2,
+            newborn: 4,
+            litter: 1,
+        };
+        let school = NonNaiveSchool::parse_with("1,2", species).unwrap();
+        assert!(table.population(&school).is_err());
+
+        let table = DescendantTable::new(Species::LANTERNFISH, 440).unwrap();
+        let school: NonNaiveSchool = vec!["0"; 1000].join(",").parse().unwrap();
+        assert!(table.population(&school).is_err());
+    }
+
+    #[test]
+    fn descendant_table_fails_exactly_when_descendants_overflow() {
+        for species in [
+            Species::LANTERNFISH,
+            Species {
+                reset: 0,
+                newborn: 2,
+                litter: 1000,
+            },
+        ] {
+            let transition = Matrix::<BigUint>::transition(&species).unwrap();
+            let fits = |days| {
+                let power = transition.pow(days, None).unwrap();
+                (0..species.timers()).all(|timer| {
+                    let descendants: BigUint = power.0.iter().map(|row| &row[timer]).sum();
+                    descendants.to_u64().is_some()
+                })
+            };
+            let last = (0..).take_while(|&days| fits(days)).last().unwrap();
+
+            assert!(DescendantTable::new(species, last).is_ok());
+            assert!(DescendantTable::new(species, last + 1).is_err());
+        }
+    }
+
+    #[test]
+    fn population_after_many_days() {
+        let school: NonNaiveSchool = "3,4,3,1,2".parse().unwrap();
+
+        assert_eq!(
+            school.population_after(1000).to_string(),
+            "379589061144698259131825683795505058481"
+        );
+        assert_eq!(
+            school.population_after_mod(1_000_000_000_000, 1_000_000_007),
+            995077479
+        );
+    }
 }
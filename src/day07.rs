@@ -1,79 +1,339 @@
+use std::cmp::Ordering;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
 use anyhow::{anyhow, bail};
 use itertools::Itertools;
+use num::BigUint;
 
+/// Crabs as their positions and how many crabs are at each, in input order. The same position can
+/// appear more than once.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Crabs {
-    crabs: Vec<i32>,
+    crabs: Vec<(i32, u64)>,
     min: i32,
     max: i32,
 }
 
-impl Crabs {
-    /// Returns Some(total_fuel_used) or None if all the fuel is used before the crabs are in
+/// Fuel a crab uses to move some distance. Distances between crabs are less than 2^32, and the
+/// fuel for any of them must fit in a `u64`.
+pub trait FuelCost {
+    fn fuel(&self, distance: u64) -> u64;
+
+    /// True if the fuel never decreases with distance and the extra fuel for each further step
+    /// never goes down, so `Crabs::align` can use a ternary search instead of trying every
     /// position.
-    fn move_crabs_to_position(&self, pos: i32) -> i32 {
-        let mut fuel_used = 0;
-        for &crab_position in self.crabs.iter() {
-            fuel_used += (pos - crab_position).abs();
-        }
-        fuel_used
+    fn is_convex(&self) -> bool {
+        false
+    }
+}
+
+/// One unit of fuel per step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Linear;
+
+impl FuelCost for Linear {
+    fn fuel(&self, distance: u64) -> u64 {
+        distance
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// One more unit of fuel for each step than the step before, starting at one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Triangular;
+
+impl FuelCost for Triangular {
+    fn fuel(&self, distance: u64) -> u64 {
+        triangular_number(distance)
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// The distance squared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quadratic;
+
+impl FuelCost for Quadratic {
+    fn fuel(&self, distance: u64) -> u64 {
+        distance * distance
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// Another cost, but never more than `cap`. Not convex even if the other cost is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capped<C> {
+    pub cost: C,
+    pub cap: u64,
+}
+
+impl<C: FuelCost> FuelCost for Capped<C> {
+    fn fuel(&self, distance: u64) -> u64 {
+        self.cost.fuel(distance).min(self.cap)
+    }
+}
+
+/// A closure cost the caller promises is convex and never decreases, see `FuelCost::is_convex`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Convex<F>(pub F);
+
+impl<F: Fn(u64) -> u64> FuelCost for Convex<F> {
+    fn fuel(&self, distance: u64) -> u64 {
+        (self.0)(distance)
     }
 
-    fn move_crabs_to_position_alt(&self, pos: i32) -> i32 {
-        let mut fuel_used = 0;
-        for &crab_position in self.crabs.iter() {
-            fuel_used += triangular_number((pos - crab_position).abs());
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// Any closure, treated as not convex. Wrap it in `Convex` if it is.
+impl<F: Fn(u64) -> u64> FuelCost for F {
+    fn fuel(&self, distance: u64) -> u64 {
+        self(distance)
+    }
+}
+
+impl Crabs {
+    /// Total fuel to move every crab to `pos`, or `None` if it doesn't fit in a `u128`.
+    fn total_fuel(&self, pos: i32, cost: &impl FuelCost) -> Option<u128> {
+        self.crabs
+            .iter()
+            .try_fold(0u128, |total, &(crab_position, count)| {
+                let distance = (pos as i64 - crab_position as i64).unsigned_abs();
+                let fuel = cost.fuel(distance) as u128 * count as u128;
+                total.checked_add(fuel)
+            })
+    }
+
+    /// Same as `total_fuel`, but never overflows.
+    fn exact_total_fuel(&self, pos: i32, cost: &impl FuelCost) -> BigUint {
+        self.crabs
+            .iter()
+            .map(|&(crab_position, count)| {
+                let distance = (pos as i64 - crab_position as i64).unsigned_abs();
+                BigUint::from(cost.fuel(distance)) * count
+            })
+            .sum()
+    }
+
+    fn move_crabs_to_position(&self, pos: i32) -> Option<u128> {
+        self.total_fuel(pos, &Linear)
+    }
+
+    fn move_crabs_to_position_alt(&self, pos: i32) -> Option<u128> {
+        self.total_fuel(pos, &Triangular)
+    }
+
+    /// The position from `positions` needing the least fuel, the lowest if there's a tie. `None`
+    /// if the fuel overflows everywhere.
+    fn cheapest(
+        &self,
+        positions: impl Iterator<Item = i32>,
+        cost: &impl FuelCost,
+    ) -> Option<Alignment> {
+        positions
+            .filter_map(|position| {
+                self.total_fuel(position, cost)
+                    .map(|fuel| Alignment { position, fuel })
+            })
+            .min_by_key(|alignment| (alignment.fuel, alignment.position))
+    }
+
+    /// The position between the outermost crabs needing the least fuel to move them all to, the
+    /// lowest if there's a tie. Convex costs are ternary searched in O(n log range), anything
+    /// else is tried at every position. `None` if the fuel overflows a `u128` everywhere.
+    pub fn align(&self, cost: &impl FuelCost) -> Option<Alignment> {
+        let (mut low, mut high) = (self.min as i64, self.max as i64);
+        if cost.is_convex() {
+            // the total is convex too, so the side with the higher third can be dropped
+            let fuel = |position: i64| self.total_fuel(position as i32, cost);
+            while high - low > 2 {
+                let third = (high - low) / 3;
+                let (left, right) = (low + third, high - third);
+                let ordering = match (fuel(left), fuel(right)) {
+                    (Some(left_fuel), Some(right_fuel)) => left_fuel.cmp(&right_fuel),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    // the overflowing totals can still differ, and the cheapest position could be
+                    // on either side
+                    (None, None) => self
+                        .exact_total_fuel(left as i32, cost)
+                        .cmp(&self.exact_total_fuel(right as i32, cost)),
+                };
+                if ordering == Ordering::Greater {
+                    low = left + 1;
+                } else if ordering == Ordering::Less {
+                    high = right - 1;
+                } else {
+                    // the least is between them, or they're on a flat least stretch
+                    high = right;
+                }
+            }
         }
-        fuel_used
+
+        self.cheapest((low as i32)..=(high as i32), cost)
     }
 
-    pub fn find_best_position(&self) -> Option<i32> {
+    pub fn find_best_position(&self) -> Option<u128> {
         (self.min..=self.max)
-            .map(|pos| self.move_crabs_to_position(pos))
+            .filter_map(|pos| self.move_crabs_to_position(pos))
             .min()
     }
 
-    pub fn find_best_position_alt(&self) -> Option<i32> {
+    pub fn find_best_position_alt(&self) -> Option<u128> {
         (self.min..=self.max)
-            .map(|pos| self.move_crabs_to_position_alt(pos))
+            .filter_map(|pos| self.move_crabs_to_position_alt(pos))
             .min()
     }
+
+    /// Same as `find_best_position`, but in O(n log n) and also says where. The total distance to
+    /// the crabs is smallest at their median, and with an even number of crabs anywhere between
+    /// the middle two, in which case this picks the lower.
+    pub fn best_alignment(&self) -> Option<Alignment> {
+        let total: u128 = self.crabs.iter().map(|&(_, count)| count as u128).sum();
+        let mut crabs = self.crabs.clone();
+        crabs.sort_unstable();
+
+        // the first position with at least half the crabs at or before it
+        let mut seen: u128 = 0;
+        let &(position, _) = crabs.iter().find(|&&(_, count)| {
+            seen += count as u128;
+            2 * seen >= total
+        })?;
+
+        self.cheapest(std::iter::once(position), &Linear)
+    }
+
+    /// Same as `find_best_position_alt`, but in O(n) and also says where, the lowest position if
+    /// there's a tie.
+    ///
+    /// The fuel to move `d` is `(d² + d) / 2`, so moving the crabs to `p` costs a sum of terms
+    /// whose slope is `p - x + sign(p - x) / 2` for a crab at `x`. The slopes cancel out within
+    /// half a step of the mean, so only the whole positions that close to it are checked.
+    pub fn best_alignment_alt(&self) -> Option<Alignment> {
+        // a u64 count of crabs at an i32 position is less than 2^95, so the sums only overflow
+        // with billions of entries
+        let (n, sum) = self
+            .crabs
+            .iter()
+            .try_fold((0i128, 0i128), |(n, sum), &(crab, count)| {
+                Some((
+                    n.checked_add(count as i128)?,
+                    sum.checked_add(crab as i128 * count as i128)?,
+                ))
+            })?;
+        // floor(mean - 1/2) and ceil(mean + 1/2), which can be just outside the crabs, from
+        // mean = quotient + remainder / n so nothing is scaled up and overflows
+        let (quotient, remainder) = (sum.div_euclid(n), sum.rem_euclid(n));
+        let low = if 2 * remainder >= n {
+            quotient
+        } else {
+            quotient - 1
+        };
+        let high = if 2 * remainder > n {
+            quotient + 2
+        } else {
+            quotient + 1
+        };
+        let low = low.max(self.min as i128);
+        let high = high.min(self.max as i128);
+
+        self.cheapest((low as i32)..=(high as i32), &Triangular)
+    }
+}
+
+/// Where the crabs line up and the fuel it takes them to get there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub position: i32,
+    pub fuel: u128,
 }
 
 impl FromStr for Crabs {
     type Err = anyhow::Error;
 
+    /// Comma-separated crabs, each either a position for a single crab or `position:count` for
+    /// that many crabs at the position. Whitespace around the crabs is ignored.
     fn from_str(s: &str) -> anyhow::Result<Self> {
-        let crabs: Vec<i32> = s
+        let crabs: Vec<(i32, u64)> = s
+            .trim()
             .split(',')
-            .map(|position| position.parse().map_err(|e: ParseIntError| anyhow!(e)))
+            .enumerate()
+            .map(|(i, crab)| parse_crab(crab.trim()).map_err(|e| anyhow!("crab {}: {}", i + 1, e)))
             .collect::<anyhow::Result<_>>()?;
 
-        let (&min, &max) = match crabs.iter().minmax() {
+        let (min, max) = match crabs.iter().map(|&(position, _)| position).minmax() {
             itertools::MinMaxResult::MinMax(min, max) => (min, max),
-            _ => bail!("Invalid input"),
+            itertools::MinMaxResult::OneElement(position) => (position, position),
+            itertools::MinMaxResult::NoElements => bail!("Invalid input"),
         };
 
         Ok(Self { crabs, min, max })
     }
 }
 
-fn triangular_number(num: i32) -> i32 {
-    ((num + 1) * num) / 2
+fn parse_crab(crab: &str) -> anyhow::Result<(i32, u64)> {
+    let (position, count) = match crab.split_once(':') {
+        Some((position, count)) => {
+            let count: u64 = count
+                .trim()
+                .parse()
+                .map_err(|e: ParseIntError| anyhow!("invalid count {:?}: {}", count, e))?;
+            if count == 0 {
+                bail!("count must be positive");
+            }
+            (position.trim(), count)
+        }
+        None => (crab, 1),
+    };
+    let position: i32 = position
+        .parse()
+        .map_err(|e: ParseIntError| anyhow!("invalid position {:?}: {}", position, e))?;
+
+    Ok((position, count))
+}
+
+/// The sum of 1 to `num`, halving whichever of `num` and `num + 1` is even first so it fits for
+/// any `num` below 2^32.
+fn triangular_number(num: u64) -> u64 {
+    if num % 2 == 0 {
+        num / 2 * (num + 1)
+    } else {
+        num.div_ceil(2) * num
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
 
     #[test]
     fn can_parse_crabs_fromstr() {
         let crabs = "1,2,3,4,5,6,7,8";
         let expected = Crabs {
-            crabs: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            crabs: vec![
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (4, 1),
+                (5, 1),
+                (6, 1),
+                (7, 1),
+                (8, 1),
+            ],
             min: 1,
             max: 8,
         };
@@ -81,6 +341,51 @@ mod tests {
         assert_eq!(crabs.parse::<Crabs>().unwrap(), expected);
     }
 
+    #[test]
+    fn can_parse_weighted_crabs() {
+        let crabs = " 0:3, 10,-4 : 2\n";
+        let expected = Crabs {
+            crabs: vec![(0, 3), (10, 1), (-4, 2)],
+            min: -4,
+            max: 10,
+        };
+        assert_eq!(crabs.parse::<Crabs>().unwrap(), expected);
+
+        let single = Crabs {
+            crabs: vec![(7, 1)],
+            min: 7,
+            max: 7,
+        };
+        assert_eq!("7".parse::<Crabs>().unwrap(), single);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |s: &str| s.parse::<Crabs>().unwrap_err().to_string();
+
+        assert_eq!(
+            error(""),
+            "crab 1: invalid position \"\": cannot parse integer from empty string"
+        );
+        assert_eq!(error("1,2:0"), "crab 2: count must be positive");
+        assert_eq!(
+            error("1,2:-1"),
+            "crab 2: invalid count \"-1\": invalid digit found in string"
+        );
+        assert_eq!(
+            error("1,2,x:3"),
+            "crab 3: invalid position \"x\": invalid digit found in string"
+        );
+        assert_eq!(
+            error("1:2:3"),
+            "crab 1: invalid count \"2:3\": invalid digit found in string"
+        );
+        assert_eq!(
+            error("2147483648"),
+            "crab 1: invalid position \"2147483648\": number too large to fit in target type"
+        );
+    }
+
     #[test]
     fn find_best_position() {
         let crabs: Crabs = "16,1,2,0,4,2,7,1,2,14".parse().unwrap();
@@ -94,4 +399,211 @@ mod tests {
 
         assert_eq!(crabs.find_best_position_alt().unwrap(), 168);
     }
+
+    #[test]
+    fn best_alignment() {
+        let crabs: Crabs = "16,1,2,0,4,2,7,1,2,14".parse().unwrap();
+
+        assert_eq!(
+            crabs.best_alignment(),
+            Some(Alignment {
+                position: 2,
+                fuel: 37
+            })
+        );
+        assert_eq!(
+            crabs.best_alignment_alt(),
+            Some(Alignment {
+                position: 5,
+                fuel: 168
+            })
+        );
+    }
+
+    #[test]
+    fn fuel_beyond_i32() {
+        // meeting halfway costs twice the 35000th triangular number
+        let crabs: Crabs = "0,70000".parse().unwrap();
+        assert_eq!(
+            crabs.best_alignment_alt(),
+            Some(Alignment {
+                position: 35000,
+                fuel: 35000 * 35001
+            })
+        );
+
+        let crabs: Crabs = "-2147483648,2147483647".parse().unwrap();
+        assert_eq!(crabs.best_alignment().unwrap().fuel, u32::MAX as u128);
+        assert_eq!(
+            crabs.best_alignment_alt().unwrap().fuel,
+            2 * triangular_number(1 << 31) as u128 - (1 << 31)
+        );
+        assert_eq!(triangular_number(u32::MAX as u64), 9223372034707292160);
+    }
+
+    #[test]
+    fn fuel_beyond_u64() {
+        let crabs: Crabs = "-2147483648:18446744073709551615,2147483647:18446744073709551615"
+            .parse()
+            .unwrap();
+        let weight = u64::MAX as u128;
+        let half = (u32::MAX / 2) as u128;
+
+        assert_eq!(
+            crabs.align(&Quadratic),
+            Some(Alignment {
+                position: -1,
+                fuel: weight * (half * half + (half + 1) * (half + 1))
+            })
+        );
+        let triangular = |distance: u128| distance * (distance + 1) / 2;
+        assert_eq!(
+            crabs.best_alignment_alt(),
+            Some(Alignment {
+                position: -1,
+                fuel: weight * (triangular(half) + triangular(half + 1))
+            })
+        );
+        // each crab alone costs nearly 2^128 wherever it goes
+        assert_eq!(crabs.align(&Convex(|_| u64::MAX)), None);
+
+        // only positions near the heavy crabs fit, and at first both sides of the search
+        // overflow
+        let crabs: Crabs = std::iter::once("-2147483648".to_string())
+            .chain(std::iter::repeat_n(
+                "2147483647:18446744073709551615".to_string(),
+                16,
+            ))
+            .join(",")
+            .parse()
+            .unwrap();
+        assert_eq!(
+            crabs.align(&Quadratic),
+            Some(Alignment {
+                position: i32::MAX,
+                fuel: (u32::MAX as u128).pow(2)
+            })
+        );
+        let crabs: Crabs = "0:18446744073709551615,10:18446744073709551615"
+            .parse()
+            .unwrap();
+        assert_eq!(crabs.align(&|_| u64::MAX), None);
+    }
+
+    /// The lowest position with the least fuel, checking every position.
+    fn brute_force(crabs: &Crabs, fuel: impl Fn(&Crabs, i32) -> Option<u128>) -> Option<Alignment> {
+        (crabs.min..=crabs.max)
+            .filter_map(|position| fuel(crabs, position).map(|fuel| Alignment { position, fuel }))
+            .min_by_key(|alignment| (alignment.fuel, alignment.position))
+    }
+
+    /// Between 2 and 51 entries, spread over up to 500 positions, a third of them for more than
+    /// one crab.
+    fn generate_crabs(rng: &mut ChaCha8Rng) -> Crabs {
+        let mut next = |bound: u64| rng.gen_range(0..bound);
+
+        let spread = 1 + next(500);
+        let crabs: Vec<String> = (0..2 + next(50))
+            .map(|_| {
+                let position = next(spread) as i64 - 100;
+                match next(3) {
+                    0 => format!("{}:{}", position, 2 + next(5)),
+                    _ => position.to_string(),
+                }
+            })
+            .collect();
+        crabs.join(",").parse().unwrap()
+    }
+
+    #[test]
+    fn best_alignment_matches_brute_force_on_generated_crabs() {
+        let mut rng = ChaCha8Rng::seed_from_u64(48);
+        for _ in 0..200 {
+            let crabs = generate_crabs(&mut rng);
+
+            let linear = crabs.best_alignment().unwrap();
+            assert_eq!(
+                linear.fuel,
+                brute_force(&crabs, Crabs::move_crabs_to_position)
+                    .unwrap()
+                    .fuel
+            );
+            assert_eq!(
+                crabs.best_alignment_alt(),
+                brute_force(&crabs, Crabs::move_crabs_to_position_alt)
+            );
+        }
+    }
+
+    #[test]
+    fn weighted_crabs_match_repeated_crabs() {
+        let mut rng = ChaCha8Rng::seed_from_u64(50);
+        for _ in 0..50 {
+            let crabs = generate_crabs(&mut rng);
+            let repeated: Vec<String> = crabs
+                .crabs
+                .iter()
+                .flat_map(|&(position, count)| {
+                    std::iter::repeat_n(position.to_string(), count as usize)
+                })
+                .collect();
+            let repeated: Crabs = repeated.join(",").parse().unwrap();
+
+            assert_eq!(crabs.best_alignment(), repeated.best_alignment());
+            assert_eq!(crabs.best_alignment_alt(), repeated.best_alignment_alt());
+            assert_eq!(crabs.align(&Quadratic), repeated.align(&Quadratic));
+        }
+    }
+
+    #[test]
+    fn align_with_cost_functions() {
+        let crabs: Crabs = "16,1,2,0,4,2,7,1,2,14".parse().unwrap();
+
+        assert_eq!(crabs.align(&Linear).unwrap().fuel, 37);
+        assert_eq!(crabs.align(&Triangular), crabs.best_alignment_alt());
+        // the mean, 4.9, rounded
+        assert_eq!(crabs.align(&Quadratic).unwrap().position, 5);
+        let capped = Capped {
+            cost: Linear,
+            cap: 3,
+        };
+        // the same as linear for most crabs, and the far ones cost 3 wherever they go
+        assert_eq!(
+            crabs.align(&capped),
+            Some(Alignment {
+                position: 2,
+                fuel: 15
+            })
+        );
+        assert_eq!(crabs.align(&|distance: u64| distance), crabs.align(&Linear));
+        assert_optimal(&crabs, &Convex(|distance: u64| distance.pow(3)));
+    }
+
+    fn assert_optimal(crabs: &Crabs, cost: &impl FuelCost) {
+        assert_eq!(
+            crabs.align(cost),
+            brute_force(crabs, |crabs, position| crabs.total_fuel(position, cost))
+        );
+    }
+
+    #[test]
+    fn align_matches_brute_force_on_generated_crabs() {
+        let mut rng = ChaCha8Rng::seed_from_u64(49);
+        for _ in 0..100 {
+            let crabs = generate_crabs(&mut rng);
+
+            assert_optimal(&crabs, &Linear);
+            assert_optimal(&crabs, &Triangular);
+            assert_optimal(&crabs, &Quadratic);
+            assert_optimal(
+                &crabs,
+                &Capped {
+                    cost: Triangular,
+                    cap: 500,
+                },
+            );
+            // not convex, cheaper to move an even distance
+            assert_optimal(&crabs, &|distance: u64| distance * (1 + 2 * (distance % 2)));
+        }
+    }
 }
@@ -60,7 +60,7 @@ impl<'a> Log<'a> {
             .iter()
             .enumerate()
             .filter(|(_, token)| token.len() == 6)
-            .find(|(_, token)| str_to_unique(**token) % left_as_products[digits[1]] != 0)
+            .find(|(_, token)| str_to_unique(token) % left_as_products[digits[1]] != 0)
             .unwrap()
             .0;
 
@@ -71,7 +71,7 @@ impl<'a> Log<'a> {
             .iter()
             .enumerate()
             .filter(|(_, token)| token.len() == 6)
-            .find(|(_, token)| str_to_unique(**token) % left_as_products[digits[4]] == 0)
+            .find(|(_, token)| str_to_unique(token) % left_as_products[digits[4]] == 0)
             .unwrap()
             .0;
 
@@ -82,7 +82,7 @@ impl<'a> Log<'a> {
             .iter()
             .enumerate()
             .filter(|(_, token)| token.len() == 5)
-            .find(|(_, token)| str_to_unique(**token) % left_as_products[digits[1]] == 0)
+            .find(|(_, token)| str_to_unique(token) % left_as_products[digits[1]] == 0)
             .unwrap()
             .0;
 
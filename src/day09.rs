@@ -58,9 +58,9 @@ impl<'a, const X: usize, const Y: usize> QuickUnion<'a, X, Y> {
         let tree_size = [[1usize; X]; Y];
         let mut trees = [[(0, 0); X]; Y];
 
-        for y in 0..Y {
-            for x in 0..X {
-                trees[y][x] = (x, y);
+        for (y, row) in trees.iter_mut().enumerate() {
+            for (x, tree) in row.iter_mut().enumerate() {
+                *tree = (x, y);
             }
         }
 